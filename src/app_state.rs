@@ -1,39 +1,238 @@
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use super::SizeItem;
+use disk_usage::{volume, NodeId, Scanner, Tree};
+use disk_usage::report::Order;
+use disk_usage::query::{self, breakdown::{self, FileGroup, Grouping}, search::{self, NameFilter}};
+use super::{SizeItem, SunburstSegment, TreemapRect};
+use duplicates::Duplicates;
+pub use duplicates::DuplicatesAction;
 
-enum Node {
-    File { name: String, size_on_disk: u64, size_real: u64 },
-    Dir { name: String, nodes: Vec<Arc<Node>> },
+mod duplicates;
+mod goto;
+mod sunburst;
+#[cfg(test)]
+#[path = "tree/test_tree.rs"]
+mod test_tree;
+mod treemap;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Metric {
+    DiskUsage,
+    EntryCount,
+}
+
+impl Metric {
+    fn value(&self, tree: &Tree, node: NodeId) -> u64 {
+        match self {
+            Metric::DiskUsage => tree.size_on_disk(node),
+            Metric::EntryCount => tree.entry_count(node),
+        }
+    }
+
+    fn toggled(&self) -> Metric {
+        match self {
+            Metric::DiskUsage => Metric::EntryCount,
+            Metric::EntryCount => Metric::DiskUsage,
+        }
+    }
+}
+
+// sizes are shown in steps of 1000, as kB and MB, or of 1024, as KiB and MiB:
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Units {
+    Decimal,
+    Binary,
+}
+
+impl Units {
+    fn base(&self) -> u64 {
+        match self {
+            Units::Decimal => 1000,
+            Units::Binary => 1024,
+        }
+    }
+
+    fn names(&self) -> [&'static str; 6] {
+        match self {
+            Units::Decimal => ["B", "kB", "MB", "GB", "TB", "PB"],
+            Units::Binary => ["B", "KiB", "MiB", "GiB", "TiB", "PiB"],
+        }
+    }
+}
+
+const LARGEST_FILES_COUNT: usize = 100;
+
+struct Breakdown {
+    grouping: Grouping,
+    groups: Vec<FileGroup>,
+    // index of the group which files are listed, if drilled down:
+    opened: Option<usize>,
+}
+
+// flat lists shown instead of the directory list, until stepped out of
+// or until a file in them is stepped into, which jumps to its directory;
+// files in them are found below the current node, their paths are relative to it:
+enum FlatView {
+    Breakdown(Breakdown),
+    LargestFiles(Vec<NodeId>),
+    Duplicates(Duplicates),
+    // files and directories below the current node with names matching the filter:
+    Search(NameFilter, Vec<NodeId>),
 }
 
-impl Node {
-    fn name(&self) -> String {
-        match &self {
-            Node::File { name, size_on_disk: _, size_real: _ } => name.to_string(),
-            Node::Dir { name, nodes: _ } => name.to_string()
+// destructive action waiting for the user to confirm it:
+struct PendingAction {
+    group: usize,
+    keep: usize,
+    action: DuplicatesAction,
+}
+
+const HISTORY_SIZE: usize = 100;
+
+const UNTOUCHED_DAYS_STEPS: [u64; 4] = [30, 90, 180, 365];
+
+#[derive(Clone)]
+struct ViewOptions {
+    metric: Metric,
+    // when set, only items untouched for at least that many days are listed:
+    untouched_days: Option<u64>,
+    // typed by the user, applies only to the list of the current node:
+    name_filter: Option<NameFilter>,
+    units: Units,
+}
+
+impl ViewOptions {
+    fn by_disk_usage() -> ViewOptions {
+        ViewOptions { metric: Metric::DiskUsage, untouched_days: None, name_filter: None, units: Units::Decimal }
+    }
+
+    fn children(&self, tree: &Tree, node: NodeId) -> Vec<NodeId> {
+        let mut children = self.nested_children(tree, node);
+        if let Some(filter) = &self.name_filter {
+            children.retain(|n| filter.matches(&tree.name(*n)));
+        }
+        children
+    }
+
+    // children of nodes below the current one, which the name filter does not apply to;
+    // the tree already sorts by size on disk, so only other metrics need sorting:
+    fn nested_children(&self, tree: &Tree, node: NodeId) -> Vec<NodeId> {
+        let mut children: Vec<NodeId> = tree.children(node).iter()
+            .copied()
+            .filter(|n| self.is_listed(tree, *n))
+            .collect();
+        if self.metric != Metric::DiskUsage {
+            children.sort_by_cached_key(|c| std::cmp::Reverse(self.metric.value(tree, *c)));
         }
+        children
     }
 
-    fn size_real(&self) -> u64 {
-        match &self {
-            Node::File { name: _, size_on_disk: _, size_real } => *size_real,
-            Node::Dir { name: _, nodes } => nodes.iter().map(|n| n.size_real()).sum()
+    fn is_listed(&self, tree: &Tree, node: NodeId) -> bool {
+        let days = match self.untouched_days {
+            Some(d) => d,
+            None => return true,
+        };
+        let cutoff = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+        match tree.last_touched(node) {
+            Some(touched) => touched < cutoff,
+            // nothing inside was ever touched, e.g. an empty directory:
+            None => true,
         }
     }
 
-    fn size_on_disk(&self) -> u64 {
-        match &self {
-            Node::File { name: _, size_on_disk, size_real: _ } => *size_on_disk,
-            Node::Dir { name: _, nodes } => nodes.iter().map(|n| n.size_on_disk()).sum()
+    fn next_untouched_days(&self) -> Option<u64> {
+        match self.untouched_days {
+            None => Some(UNTOUCHED_DAYS_STEPS[0]),
+            Some(days) => UNTOUCHED_DAYS_STEPS.iter()
+                .copied()
+                .find(|d| *d > days),
         }
     }
+
+    fn description(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if self.metric == Metric::EntryCount {
+            parts.push("by number of entries".to_string());
+        }
+        if let Some(days) = self.untouched_days {
+            parts.push(format!("untouched for {} days", days));
+        }
+        if let Some(filter) = &self.name_filter {
+            parts.push(filter.description());
+        }
+        parts.join(", ")
+    }
 }
 
 struct RootAndNavigation {
-    root_node: Arc<Node>,
-    navigation: Vec<Arc<Node>>,
+    // shared with the duplicates search while it hashes files, copied on write if still shared:
+    tree: Arc<Tree>,
+    navigation: Vec<NodeId>,
+    view: ViewOptions,
+    flat_view: Option<FlatView>,
+    // cursor in the directory list to return to when the flat view is closed:
+    flat_view_return_cursor: usize,
+    pending_action: Option<PendingAction>,
+    // navigation stacks of previously visited locations, most recent last:
+    back: Vec<Vec<NodeId>>,
+    forward: Vec<Vec<NodeId>>,
+    // whether the tree was scanned from the file system, rather than listed on stdin or imported,
+    // so that its files can be read and replaced:
+    on_file_system: bool,
+    // roots listed in the volume picker, in the order of its items:
+    volumes: Vec<PathBuf>,
+}
+
+impl RootAndNavigation {
+    fn current_node(&self) -> NodeId {
+        self.navigation.last().copied().unwrap_or(self.tree.root())
+    }
+
+    // directories below the root start their list with the ".." item:
+    fn up_item_offset(&self) -> usize {
+        if self.navigation.is_empty() { 0 } else { 1 }
+    }
+
+    fn current_path(&self) -> PathBuf {
+        self.tree.path(self.current_node())
+    }
+
+    // ids of the remaining nodes stay the same, so navigation, history and flat views are still valid:
+    fn remove_files(&mut self, files: &[NodeId]) {
+        Arc::make_mut(&mut self.tree).remove(files);
+    }
+
+    // files replaced with hard links to another one stay, only without space on disk of their own:
+    fn unlink_files(&mut self, files: &[NodeId]) {
+        Arc::make_mut(&mut self.tree).unlink_space(files);
+    }
+
+    // to be called before navigation changes, so that going back returns here;
+    // the name filter was typed for the list being left, so it goes away with it:
+    fn leave_location(&mut self) {
+        self.view.name_filter = None;
+        let navigation = self.navigation.clone();
+        push_to_history(&mut self.back, navigation);
+        self.forward.clear();
+    }
+
+    fn open_flat_view(&mut self, cursor: i32, flat_view: FlatView) {
+        if self.flat_view.is_none() {
+            self.flat_view_return_cursor = cursor.max(0) as usize;
+        }
+        self.flat_view = Some(flat_view);
+    }
+}
+
+// the oldest locations are forgotten once there are too many:
+fn push_to_history(history: &mut Vec<Vec<NodeId>>, navigation: Vec<NodeId>) {
+    history.push(navigation);
+    if history.len() > HISTORY_SIZE {
+        history.remove(0);
+    }
 }
 
 pub struct AppState {
@@ -45,295 +244,936 @@ impl AppState {
         AppState {
             state: Mutex::new(
                 RootAndNavigation {
-                    root_node: Arc::new(Node::Dir { name: "{root node}".to_string(), nodes: Vec::new() }),
+                    tree: Arc::new(Tree::new(Path::new(""))),
                     navigation: Vec::new(),
+                    view: ViewOptions::by_disk_usage(),
+                    flat_view: None,
+                    flat_view_return_cursor: 0,
+                    pending_action: None,
+                    back: Vec::new(),
+                    forward: Vec::new(),
+                    on_file_system: false,
+                    volumes: Vec::new(),
                 }
             ),
         }
     }
 
-    pub fn scan_root_from(&self, path: PathBuf) -> Vec<SizeItem> {
+    pub fn scan_root_from(&self, path: PathBuf, scanner: &Scanner) -> io::Result<Vec<SizeItem>> {
+        Ok(self.replace_tree(scanner.scan(&path)?, true))
+    }
+
+    // more than one path ends up below a common synthetic root:
+    pub fn scan_roots_from(&self, mut paths: Vec<PathBuf>, scanner: &Scanner) -> io::Result<Vec<SizeItem>> {
+        if paths.len() == 1 {
+            return self.scan_root_from(paths.remove(0), scanner);
+        }
+        Ok(self.replace_tree(scanner.scan_all(&paths)?, true))
+    }
+
+    // for trees which did not come from scanning, like path lists;
+    // node ids of the previous tree mean nothing in the new one, so everything pointing at them goes:
+    pub fn show_tree(&self, tree: Tree) -> Vec<SizeItem> {
+        self.replace_tree(tree, false)
+    }
+
+    fn replace_tree(&self, tree: Tree, on_file_system: bool) -> Vec<SizeItem> {
         {
-            let cluster_size = files::cluster_size_for_path(&path);
-            let node = files::scan_dir_recursive_depth_first(&path, &cluster_size);
             let mut state = self.state.lock()
                 .expect("Failed to acquire mutex lock on state");
-            state.root_node = Arc::new(node);
+            state.tree = Arc::new(tree);
+            state.on_file_system = on_file_system;
+            state.navigation.clear();
+            state.back.clear();
+            state.forward.clear();
+            state.flat_view = None;
+            state.pending_action = None;
+            state.view.name_filter = None;
         }
-        return self.root_size_items();
+        self.root_size_items()
+    }
+
+    // ranking asked for on the command line, before anything is shown:
+    pub fn rank_by(&self, order: Order) {
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .view.metric = match order {
+                Order::DiskUsage => Metric::DiskUsage,
+                Order::EntryCount => Metric::EntryCount,
+            };
+    }
+
+    pub fn show_sizes_in(&self, units: Units) {
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .view.units = units;
+    }
+
+    // mounted volumes with their used and free space, remembered for picking one by index:
+    pub fn list_volumes(&self) -> Vec<SizeItem> {
+        let volumes = volume::mounted_volumes();
+        let units = self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .view.units;
+        let items = volumes.iter()
+            .map(|v| ui::volume_to_size_item(v, volume::volume_label(v), volume::volume_space(v), units))
+            .collect();
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .volumes = volumes;
+        items
+    }
+
+    pub fn volume_path(&self, index: i32) -> Option<PathBuf> {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        usize::try_from(index).ok()
+            .and_then(|i| state.volumes.get(i))
+            .cloned()
     }
 
     pub fn step_out(&self) -> Option<(usize, Vec<SizeItem>)> {
         let mut state = self.state.lock()
             .expect("Failed to acquire mutex lock on state");
-        let nav = &mut state.navigation;
-        if nav.len() == 0 {
+        let tree = Arc::clone(&state.tree);
+        let units = state.view.units;
+        match state.flat_view.as_mut() {
+            Some(FlatView::Breakdown(breakdown)) => if let Some(group) = breakdown.opened.take() {
+                return Some((group, ui::groups_to_size_items(&tree, &breakdown.groups, units)));
+            },
+            Some(FlatView::Duplicates(duplicates)) => if let Some(group) = duplicates.opened.take() {
+                return Some((group, ui::duplicate_groups_to_size_items(&tree, &duplicates.groups, units)));
+            },
+            _ => {}
+        }
+        if state.flat_view.take().is_some() {
+            let has_up_item = state.up_item_offset() > 0;
+            return Some((state.flat_view_return_cursor, ui::node_to_size_items(&state.tree, state.current_node(), &state.view, has_up_item)));
+        }
+        if state.navigation.is_empty() {
             // we are already on the root node, ignoring:
             return None;
         }
+        state.leave_location();
+        let nav = &mut state.navigation;
         let nav_len = nav.len();
         let current = nav.remove(nav_len - 1);
-        let one_up: Arc<Node> = if nav.len() > 0 {
-            Arc::clone(&nav[nav.len() - 1])
-        } else {
-            Arc::clone(&state.root_node)
-        };
-        let index = self.index_of_subnode_in_node(&current, &one_up);
-        return Some((index, ui::node_to_size_items(one_up)));
+        let one_up = state.current_node();
+        let view = state.view.clone();
+        let offset = state.up_item_offset();
+        // the directory left may be hidden by the view, then the list starts from the top:
+        let index = self.index_of_subnode_in_node(&state.tree, current, one_up, &view)
+            .map(|i| i + offset)
+            .unwrap_or(0);
+        Some((index, ui::node_to_size_items(&state.tree, one_up, &view, offset > 0)))
     }
 
-    pub fn step_into(&self, index: i32) -> Option<Vec<SizeItem>> {
-        // TODO: add support for item 0 being an up folder
+    pub fn step_into(&self, index: i32) -> Option<(usize, Vec<SizeItem>)> {
+        {
+            let mut state = self.state.lock()
+                .expect("Failed to acquire mutex lock on state");
+            if state.flat_view.is_some() {
+                return self.step_into_flat_view(&mut state, index);
+            }
+        }
+        if index == 0 && self.up_item_offset() > 0 {
+            return self.step_out();
+        }
         let subnode_result = self.subnode_with_index(index);
         let target_node = match subnode_result {
-            Ok(arc) => arc,
+            Ok(id) => id,
             Err(e) => {
                 eprintln!("{}", e);
-                return Some(self.clear_navigation_and_return_to_root());
+                return Some((0, self.clear_navigation_and_return_to_root()));
             }
         };
-        match target_node.as_ref() {
-            Node::File { name: _, size_on_disk: _, size_real: _ } => {
-                eprintln!("On step into operation, attempting to step into a file, ignoring.");
-                None
-            }
-            Node::Dir { name: _, nodes } => {
-                self.state.lock()
-                    .expect("Failed to acquire mutex lock on navigation")
-                    .navigation
-                    .push(Arc::clone(&target_node));
-                let items: Vec<SizeItem> = ui::subnodes_to_size_items(&nodes);
-                Some(items)
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on navigation");
+        if !state.tree.is_dir(target_node) {
+            eprintln!("On step into operation, attempting to step into a file, ignoring.");
+            return None;
+        }
+        state.leave_location();
+        state.navigation.push(target_node);
+        let items: Vec<SizeItem> = ui::subnodes_to_size_items(&state.tree, target_node, &state.view, true);
+        Some((0, items))
+    }
+
+    // depth is the number of directories below the root to keep in navigation,
+    // the cursor ends up on the directory which was stepped out of:
+    pub fn step_out_to(&self, depth: i32) -> Option<(usize, Vec<SizeItem>)> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        if depth < 0 || depth as usize > state.navigation.len() {
+            return None;
+        }
+        let depth = depth as usize;
+        let flat_view = state.flat_view.take();
+        if depth == state.navigation.len() {
+            return match flat_view {
+                Some(_) => {
+                    let has_up_item = state.up_item_offset() > 0;
+                    Some((state.flat_view_return_cursor, ui::node_to_size_items(&state.tree, state.current_node(), &state.view, has_up_item)))
+                }
+                None => None,
+            };
+        }
+        let stepped_out_of = state.navigation[depth];
+        state.leave_location();
+        state.navigation.truncate(depth);
+        let current = state.current_node();
+        let view = state.view.clone();
+        let offset = state.up_item_offset();
+        let index = self.index_of_subnode_in_node(&state.tree, stepped_out_of, current, &view)
+            .map(|i| i + offset)
+            .unwrap_or(0);
+        Some((index, ui::node_to_size_items(&state.tree, current, &view, offset > 0)))
+    }
+
+    pub fn go_back(&self) -> Option<(usize, Vec<SizeItem>)> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let previous = state.back.pop()?;
+        let left = std::mem::replace(&mut state.navigation, previous);
+        push_to_history(&mut state.forward, left.clone());
+        Some(self.show_location(&mut state, &left))
+    }
+
+    pub fn go_forward(&self) -> Option<(usize, Vec<SizeItem>)> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let next = state.forward.pop()?;
+        let left = std::mem::replace(&mut state.navigation, next);
+        push_to_history(&mut state.back, left.clone());
+        Some(self.show_location(&mut state, &left))
+    }
+
+    // path typed by the user, either absolute or relative to the current directory;
+    // for a file the containing directory is shown with the cursor on the file:
+    pub fn go_to_path(&self, text: &str) -> Result<(usize, Vec<SizeItem>), String> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let mut navigation = goto::resolve(&state.tree, &state.navigation, text)?;
+        let file = match navigation.last() {
+            Some(node) if !state.tree.is_dir(*node) => navigation.pop(),
+            _ => None,
+        };
+        state.leave_location();
+        state.navigation = navigation;
+        state.flat_view = None;
+        let current = state.current_node();
+        let view = state.view.clone();
+        let offset = state.up_item_offset();
+        let cursor = file
+            .and_then(|f| self.index_of_subnode_in_node(&state.tree, f, current, &view))
+            .map(|i| i + offset)
+            .unwrap_or(0);
+        Ok((cursor, ui::node_to_size_items(&state.tree, current, &view, offset > 0)))
+    }
+
+    pub fn complete_path(&self, text: &str) -> String {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        goto::complete(&state.tree, &state.navigation, text)
+    }
+
+    // the go to path prompt starts with it, ready for typing a name inside the current directory:
+    pub fn current_path_text(&self) -> String {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let mut text = state.current_path().display().to_string();
+        if !text.ends_with(std::path::is_separator) {
+            text.push(std::path::MAIN_SEPARATOR);
+        }
+        text
+    }
+
+    pub fn switch_metric(&self, cursor: i32) -> (usize, Vec<SizeItem>) {
+        self.change_view(cursor, |view| view.metric = view.metric.toggled())
+    }
+
+    pub fn cycle_untouched_filter(&self, cursor: i32) -> (usize, Vec<SizeItem>) {
+        self.change_view(cursor, |view| view.untouched_days = view.next_untouched_days())
+    }
+
+    // opens breakdown of files below the current node, or switches its grouping if already open:
+    pub fn open_breakdown(&self, cursor: i32) -> Vec<SizeItem> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let grouping = match &state.flat_view {
+            Some(FlatView::Breakdown(b)) => b.grouping.toggled(),
+            _ => Grouping::Extension,
+        };
+        let current = state.current_node();
+        let groups = breakdown::group_files(&state.tree, query::files_below(&state.tree, current), grouping);
+        let items = ui::groups_to_size_items(&state.tree, &groups, state.view.units);
+        state.open_flat_view(cursor, FlatView::Breakdown(Breakdown { grouping, groups, opened: None }));
+        items
+    }
+
+    // filters the list of the current node as the pattern is typed:
+    pub fn set_name_filter(&self, cursor: i32, pattern: &str, fuzzy: bool) -> (usize, Vec<SizeItem>) {
+        let filter = NameFilter::new(pattern, fuzzy);
+        self.change_view(cursor, |view| view.name_filter = filter)
+    }
+
+    // lists everything below the current node matching the pattern, jumping to a match goes to its parent:
+    pub fn search(&self, cursor: i32, pattern: &str, fuzzy: bool) -> Option<Vec<SizeItem>> {
+        let filter = NameFilter::new(pattern, fuzzy)?;
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let current = state.current_node();
+        let matches = search::find_matches(&state.tree, current, &filter);
+        let items = ui::found_files_to_size_items(&state.tree, current, &matches, state.view.units);
+        state.open_flat_view(cursor, FlatView::Search(filter, matches));
+        Some(items)
+    }
+
+    pub fn open_largest_files(&self, cursor: i32) -> Vec<SizeItem> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let current = state.current_node();
+        let files = query::largest_files_below(&state.tree, current, LARGEST_FILES_COUNT);
+        let items = ui::found_files_to_size_items(&state.tree, current, &files, state.view.units);
+        state.open_flat_view(cursor, FlatView::LargestFiles(files));
+        items
+    }
+
+    // hashes file contents, so it is meant to be called outside of the UI thread;
+    // returns nothing if the user navigated elsewhere in the meantime:
+    pub fn open_duplicates(&self, cursor: i32) -> Option<Vec<SizeItem>> {
+        let (tree, current, base) = {
+            let state = self.state.lock()
+                .expect("Failed to acquire mutex lock on state");
+            // listed or imported paths are not read, so files of such trees may not even exist here:
+            if !state.on_file_system {
+                return None;
             }
+            (Arc::clone(&state.tree), state.current_node(), state.current_path())
+        };
+        let duplicates = duplicates::find_duplicates(&tree, base, current, query::files_below(&tree, current));
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        // ids are only valid in the tree they come from, which a rescan replaces:
+        if !Arc::ptr_eq(&tree, &state.tree) || current != state.current_node() {
+            return None;
         }
+        let items = ui::duplicate_groups_to_size_items(&state.tree, &duplicates.groups, state.view.units);
+        state.open_flat_view(cursor, FlatView::Duplicates(duplicates));
+        Some(items)
     }
 
-    fn clear_navigation_and_return_to_root(&self) -> Vec<SizeItem> {
-        self.state.lock()
-            .expect("Failed to acquire lock for clearing")
-            .navigation.clear();
-        return self.root_size_items();
+    // in the list of groups the first file is kept, inside a group the one under the cursor:
+    pub fn prepare_duplicates_action(&self, cursor: i32, action: DuplicatesAction) -> Option<String> {
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let duplicates = match &state.flat_view {
+            Some(FlatView::Duplicates(d)) => d,
+            _ => return None,
+        };
+        if cursor < 0 {
+            return None;
+        }
+        let (group, keep) = match duplicates.opened {
+            Some(group) => (group, cursor as usize),
+            None => (cursor as usize, 0),
+        };
+        let duplicate_group = duplicates.groups.get(group)?;
+        let kept = duplicate_group.files.get(keep)?;
+        let message = format!("{} {} duplicates of {}, reclaiming {}?",
+                              action.verb(),
+                              duplicate_group.files.len() - 1,
+                              state.tree.relative_path(duplicates.base_node, *kept),
+                              ui::readable_size(&duplicate_group.reclaimable(&state.tree), state.view.units));
+        state.pending_action = Some(PendingAction { group, keep, action });
+        Some(message)
     }
 
-    fn root_size_items(&self) -> Vec<SizeItem> {
-        ui::node_ref_to_size_items(
-            &self.state.lock()
-                .expect("Failed to acquire mutex lock on root node").root_node)
+    pub fn cancel_pending_action(&self) {
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .pending_action = None;
     }
 
-    fn subnode_with_index(&self, index: i32) -> Result<Arc<Node>, &str> {
-        if index < 0 {
-            return Err("On step into operation, attempting to step into element outside of elements size, ignoring.");
+    // reads and modifies files, so it is meant to be called outside of the UI thread,
+    // while the UI does not allow anything else to be done;
+    // returns list of remaining duplicate groups and the cursor in it:
+    pub fn confirm_pending_action(&self) -> Option<(usize, Vec<SizeItem>)> {
+        // files are not touched while holding the lock, so that the UI is not blocked meanwhile:
+        let (tree, pending, paths) = {
+            let mut state = self.state.lock()
+                .expect("Failed to acquire mutex lock on state");
+            let pending = state.pending_action.take()?;
+            let duplicates = match &state.flat_view {
+                Some(FlatView::Duplicates(d)) => d,
+                _ => return None,
+            };
+            let paths: Vec<PathBuf> = duplicates.groups.get(pending.group)?.files.iter()
+                .map(|f| duplicates.path_of(&state.tree, *f))
+                .collect();
+            (Arc::clone(&state.tree), pending, paths)
+        };
+        let done = duplicates::apply_action(&paths, pending.keep, pending.action);
+        // deleted files are gone, replaced ones became names of the kept file:
+        let remaining: Vec<usize> = (0..paths.len())
+            .filter(|i| pending.action == DuplicatesAction::HardLink || !done.contains(i))
+            .collect();
+        let copies = duplicates::count_copies(remaining.iter().map(|i| paths[*i].clone()));
+        let mut state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        // ids are only valid in the tree they come from, which a rescan replaces:
+        if !Arc::ptr_eq(&tree, &state.tree) {
+            return None;
         }
-        let current_node = self.current_node();
-        let subnodes: &Vec<Arc<Node>> = match current_node.as_ref() {
-            Node::File { name: _, size_on_disk: _, size_real: _ } => {
-                panic!("On step into operation, current node appears to be a file rather than a dir. App state got corrupted.");
+        // not shared anymore, so that the tree is changed in place:
+        drop(tree);
+        let changed: Vec<NodeId> = match &state.flat_view {
+            Some(FlatView::Duplicates(d)) => {
+                let files = &d.groups.get(pending.group)?.files;
+                done.iter().filter_map(|i| files.get(*i).copied()).collect()
             }
-            Node::Dir { name: _, nodes } => &nodes
+            _ => return None,
         };
-        if index >= subnodes.len() as i32 {
-            return Err("On step into operation, attempting to step into element outside of elements size, ignoring.");
+        match pending.action {
+            DuplicatesAction::Delete => state.remove_files(&changed),
+            DuplicatesAction::HardLink => state.unlink_files(&changed),
+        }
+        let tree = Arc::clone(&state.tree);
+        let units = state.view.units;
+        let duplicates = match state.flat_view.as_mut() {
+            Some(FlatView::Duplicates(d)) => d,
+            _ => return None,
+        };
+        // files which failed stay listed, as long as there is still something to reclaim:
+        let group = duplicates.groups.get_mut(pending.group)?;
+        let mut index = 0;
+        group.files.retain(|_| {
+            index += 1;
+            remaining.contains(&(index - 1))
+        });
+        group.copies = copies;
+        if group.copies <= 1 {
+            duplicates.groups.remove(pending.group);
+        }
+        duplicates.opened = None;
+        let cursor = pending.group.min(duplicates.groups.len().saturating_sub(1));
+        Some((cursor, ui::duplicate_groups_to_size_items(&tree, &duplicates.groups, units)))
+    }
+
+    // aspect is the width of the treemap divided by its height;
+    // flat views are not laid out, as their items are not children of the current node:
+    pub fn treemap(&self, aspect: f32) -> Vec<TreemapRect> {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        if state.flat_view.is_some() || aspect.is_nan() || aspect <= 0.0 {
+            return Vec::new();
         }
-        let selected_node = &subnodes[index as usize];
-        Ok(Arc::clone(selected_node))
+        let mut rects = ui::node_to_treemap_rects(&state.tree, state.current_node(), &state.view, aspect as f64);
+        // rectangles point at list items, which are shifted by the ".." item:
+        let offset = state.up_item_offset() as i32;
+        rects.iter_mut().for_each(|r| r.index += offset);
+        rects
     }
 
-    fn current_node(&self) -> Arc<Node> {
+    // like the treemap, only directory lists are drawn:
+    pub fn sunburst(&self) -> Vec<SunburstSegment> {
         let state = self.state.lock()
             .expect("Failed to acquire mutex lock on state");
-        let nav = &state.navigation;
-        let current_node = if nav.len() == 0 {
-            &state.root_node
+        if state.flat_view.is_some() {
+            return Vec::new();
+        }
+        let mut segments = ui::node_to_sunburst_segments(&state.tree, state.current_node(), &state.view);
+        let offset = state.up_item_offset() as i32;
+        segments.iter_mut().for_each(|s| s.index += offset);
+        segments
+    }
+
+    // the scanned root path followed by names of directories stepped into:
+    pub fn breadcrumbs(&self) -> Vec<String> {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let tree = &state.tree;
+        let first = if !tree.root_path().as_os_str().is_empty() {
+            tree.root_path().display().to_string()
+        } else if !tree.children(tree.root()).is_empty() {
+            // several roots scanned together, each named by its path:
+            format!("{} roots", tree.children(tree.root()).len())
         } else {
-            &nav[nav.len() - 1]
+            return Vec::new();
         };
-        return Arc::clone(current_node);
+        let mut breadcrumbs = vec![first];
+        breadcrumbs.extend(state.navigation.iter().map(|n| state.tree.name(*n).to_string()));
+        breadcrumbs
     }
 
-    fn index_of_subnode_in_node(&self, subnode: &Node, node: &Node) -> usize {
-        let subnodes = match node {
-            Node::File { name: _, size_on_disk: _, size_real: _ } => return 0,
-            Node::Dir { name: _, nodes } => nodes,
-        };
-        let mut index: usize = 0;
-        for n in subnodes {
-            let nn: &Node = n;
-            // TODO implement Eq or something to make this check cleaner:
-            if subnode.name() == nn.name()
-                && subnode.size_real() == nn.size_real() {
-                return index;
+    // total size and number of items of the current directory, with space left on its volume:
+    pub fn status(&self) -> String {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let tree = &state.tree;
+        let current = state.current_node();
+        let items = if tree.is_dir(current) { state.view.children(tree, current).len() } else { 1 };
+        let mut parts = vec![format!("{} ({} on disk), {} items",
+                                     ui::readable_size(&tree.size_real(current), state.view.units),
+                                     ui::readable_size(&tree.size_on_disk(current), state.view.units),
+                                     items)];
+        // the root above several scanned roots is on no volume:
+        if !state.current_path().as_os_str().is_empty() {
+            if let Some((free, total)) = volume::volume_space(&state.current_path()) {
+                parts.push(format!("{} free of {}", ui::readable_size(&free, state.view.units), ui::readable_size(&total, state.view.units)));
             }
-            index = index + 1;
         }
-        return 0;
+        parts.join(" | ")
     }
-}
 
-mod files {
-    use std::cmp::Ordering;
-    use std::fs::{metadata, read_dir};
-    use std::path::PathBuf;
-    use std::sync::Arc;
-    use windows::core::{HSTRING};
-    use windows::Win32::Storage::FileSystem::{GetVolumePathNameW, GetDiskFreeSpaceW };
-    use super::Node;
-
-    pub(super) fn cluster_size_for_path(path: &PathBuf) -> u64 {
-        let root_path: &mut [u16] = &mut [0; 261];
-        let path_result = unsafe { GetVolumePathNameW(&HSTRING::from(path.as_path()), root_path) };
-        if !path_result.as_bool() {
-            // TODO: make it Result instead
-            panic!("Could not determine volumen name for path");
-        }
-        let mut sectors_per_cluster: u32 = 0;
-        let mut bytes_per_sector: u32 = 0;
-        let root_disk = HSTRING::from_wide(root_path).expect("Failed to convert [u16] into HSTRING");
-        let disk_free_result = unsafe { GetDiskFreeSpaceW(&root_disk, Some(&mut sectors_per_cluster), Some(&mut bytes_per_sector), None, None) };
-        if !disk_free_result.as_bool() {
-            // TODO: make it Result instead
-            panic!("Could not determine cluster size for disk");
-        }
-        u64::from(sectors_per_cluster) * u64::from(bytes_per_sector)
-    }
-
-    pub(super) fn scan_dir_recursive_depth_first(path: &PathBuf, cluster_size: &u64) -> Node {
-        if path.is_dir() {
-            let reading_dir = read_dir(path);
-            match reading_dir {
-                Ok(rd) => {
-                    let mut nodes: Vec<Arc<Node>> = Vec::new();
-                    for entry in rd {
-                        match entry {
-                            Ok(dir_entry) => {
-                                let p = dir_entry.path();
-                                nodes.push(Arc::new(scan_dir_recursive_depth_first(&p, cluster_size)));
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to process dir entry because of: {:?}", e);
-                            }
-                        }
-                    }
-                    nodes.sort_by(|a, b| {
-                        let diff = a.size_on_disk() as i128 - b.size_on_disk() as i128;
-                        if diff < 0 {
-                            return Ordering::Greater;
-                        }
-                        if diff > 0 {
-                            return Ordering::Less;
-                        }
-                        Ordering::Equal
-                    });
-                    return Node::Dir { name: path_file_name(path), nodes };
+    pub fn view_description(&self) -> String {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let mut parts: Vec<String> = Vec::new();
+        match &state.flat_view {
+            Some(FlatView::Breakdown(b)) => {
+                parts.push(b.grouping.description().to_string());
+                if let Some(group) = b.opened {
+                    parts.push(b.groups[group].name.clone());
+                }
+            }
+            Some(FlatView::LargestFiles(files)) => parts.push(format!("{} largest files", files.len())),
+            Some(FlatView::Search(filter, matches)) => parts.push(format!("{} found {}", matches.len(), filter.description())),
+            Some(FlatView::Duplicates(d)) => {
+                let reclaimable: u64 = d.groups.iter().map(|g| g.reclaimable(&state.tree)).sum();
+                parts.push(format!("duplicates, {} reclaimable", ui::readable_size(&reclaimable, state.view.units)));
+                if !d.unreadable.is_empty() {
+                    parts.push(format!("{} unreadable files skipped", d.unreadable.len()));
                 }
-                Err(e) => {
-                    eprintln!("Failed to read dir: {:?}, because of: {:?}", path, e);
-                    return Node::File {
-                        name: path_file_name(path),
-                        size_on_disk: 0,
-                        size_real: 0,
-                    };
+                if let Some(group) = d.opened {
+                    parts.push(state.tree.name(d.groups[group].files[0]).to_string());
                 }
             }
+            None => {}
         }
-        if path.is_file() {
-            let name = path_file_name(path);
-            let size = path_file_size(path);
-            let size_on_disk = ((size + cluster_size - 1) / cluster_size) * cluster_size;
-            return Node::File {
-                name: name,
-                size_on_disk: size_on_disk,
-                size_real: size,
-            };
-        }
-        Node::File {
-            name: path_file_name(path),
-            size_on_disk: 0,
-            size_real: 0,
-        }
+        parts.push(state.view.description());
+        parts.retain(|p| !p.is_empty());
+        parts.join(", ")
     }
 
-    fn path_file_name(path: &PathBuf) -> String {
-        path.file_name()
-            .map(|s| s.to_os_string())
-            .and_then(|s| {
-                match s.into_string() {
-                    Ok(o) => Some(o),
-                    Err(e) => {
-                        eprintln!("Failed to convert OsString into String: {:?}", e);
-                        None
+    fn step_into_flat_view(&self, state: &mut RootAndNavigation, index: i32) -> Option<(usize, Vec<SizeItem>)> {
+        if index < 0 {
+            return None;
+        }
+        let index = index as usize;
+        let tree = Arc::clone(&state.tree);
+        let base = state.current_node();
+        let units = state.view.units;
+        let found = match state.flat_view.as_mut() {
+            Some(FlatView::Breakdown(breakdown)) => match breakdown.opened {
+                Some(group) => breakdown.groups[group].files.get(index),
+                None => {
+                    if index >= breakdown.groups.len() {
+                        return None;
                     }
+                    breakdown.opened = Some(index);
+                    return Some((0, ui::found_files_to_size_items(&tree, base, &breakdown.groups[index].files, units)));
                 }
-            })
-            .unwrap_or("<invalid name>".to_string())
+            },
+            Some(FlatView::LargestFiles(files)) => files.get(index),
+            Some(FlatView::Search(_, matches)) => matches.get(index),
+            Some(FlatView::Duplicates(duplicates)) => match duplicates.opened {
+                Some(group) => duplicates.groups[group].files.get(index),
+                None => {
+                    if index >= duplicates.groups.len() {
+                        return None;
+                    }
+                    duplicates.opened = Some(index);
+                    return Some((0, ui::found_files_to_size_items(&tree, base, &duplicates.groups[index].files, units)));
+                }
+            },
+            None => None,
+        };
+        let file = match found {
+            Some(f) => *f,
+            None => return None,
+        };
+        // jumping to the directory containing the file, with cursor on the file:
+        state.flat_view = None;
+        state.leave_location();
+        state.navigation.extend(tree.parents_below(base, file));
+        let directory = state.current_node();
+        let offset = state.up_item_offset();
+        let cursor = self.index_of_subnode_in_node(&tree, file, directory, &state.view)
+            .map(|i| i + offset)
+            .unwrap_or(0);
+        Some((cursor, ui::node_to_size_items(&tree, directory, &state.view, offset > 0)))
     }
 
-    fn path_file_size(path: &PathBuf) -> u64 {
-        let s = metadata(path).map(|md| md.len());
-        match s {
-            Ok(size) => size,
-            Err(e) => {
-                eprintln!("Failed to read size of the file: {:?} because of {:?}", path, e);
-                0
+    // when returning to a parent of the location left, the cursor is put on the directory it was in:
+    fn show_location(&self, state: &mut RootAndNavigation, left: &[NodeId]) -> (usize, Vec<SizeItem>) {
+        state.flat_view = None;
+        state.view.name_filter = None;
+        let depth = state.navigation.len();
+        let current = state.current_node();
+        let view = state.view.clone();
+        let offset = state.up_item_offset();
+        let is_parent = left.len() > depth
+            && left.iter().zip(state.navigation.iter()).all(|(a, b)| a == b);
+        let cursor = if is_parent {
+            self.index_of_subnode_in_node(&state.tree, left[depth], current, &view)
+                .map(|i| i + offset)
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        (cursor, ui::node_to_size_items(&state.tree, current, &view, offset > 0))
+    }
+
+    // keeps the cursor on the same item if it is still listed after the change:
+    fn change_view<F: FnOnce(&mut ViewOptions)>(&self, cursor: i32, change: F) -> (usize, Vec<SizeItem>) {
+        // view options apply to the directory list, so the flat view is closed first:
+        let cursor = {
+            let mut state = self.state.lock()
+                .expect("Failed to acquire mutex lock on state");
+            match state.flat_view.take() {
+                Some(_) => state.flat_view_return_cursor as i32,
+                None => cursor,
             }
+        };
+        let selected = self.subnode_with_index(cursor).ok();
+        let current = self.current_node();
+        let (tree, view) = {
+            let mut state = self.state.lock()
+                .expect("Failed to acquire mutex lock on state");
+            change(&mut state.view);
+            (Arc::clone(&state.tree), state.view.clone())
+        };
+        let offset = self.up_item_offset();
+        // the selected item may no longer be listed, then the list starts from the top:
+        let index = selected
+            .and_then(|node| self.index_of_subnode_in_node(&tree, node, current, &view))
+            .map(|i| i + offset)
+            .unwrap_or(0);
+        (index, ui::node_to_size_items(&tree, current, &view, offset > 0))
+    }
+
+    fn clear_navigation_and_return_to_root(&self) -> Vec<SizeItem> {
+        self.state.lock()
+            .expect("Failed to acquire lock for clearing")
+            .navigation.clear();
+        self.root_size_items()
+    }
+
+    fn root_size_items(&self) -> Vec<SizeItem> {
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on root node");
+        ui::node_to_size_items(&state.tree, state.tree.root(), &state.view, false)
+    }
+
+    // index is the one in the list, so the ".." item is not a subnode:
+    fn subnode_with_index(&self, index: i32) -> Result<NodeId, &str> {
+        let index = index - self.up_item_offset() as i32;
+        if index < 0 {
+            return Err("On step into operation, attempting to step into element outside of elements size, ignoring.");
         }
+        let state = self.state.lock()
+            .expect("Failed to acquire mutex lock on state");
+        let current_node = state.current_node();
+        if !state.tree.is_dir(current_node) {
+            panic!("On step into operation, current node appears to be a file rather than a dir. App state got corrupted.");
+        }
+        let subnodes = state.view.children(&state.tree, current_node);
+        if index >= subnodes.len() as i32 {
+            return Err("On step into operation, attempting to step into element outside of elements size, ignoring.");
+        }
+        Ok(subnodes[index as usize])
+    }
+
+    fn current_node(&self) -> NodeId {
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .current_node()
+    }
+
+    fn up_item_offset(&self) -> usize {
+        self.state.lock()
+            .expect("Failed to acquire mutex lock on state")
+            .up_item_offset()
+    }
+
+    // ids are unique within the tree, so the very same node is looked for,
+    // not one which happens to have the same name and size;
+    // the index is among the subnodes, not counting the ".." item:
+    fn index_of_subnode_in_node(&self, tree: &Tree, subnode: NodeId, node: NodeId, view: &ViewOptions) -> Option<usize> {
+        view.children(tree, node).iter().position(|n| *n == subnode)
     }
 }
 
 mod ui {
-    use std::sync::Arc;
-    use super::Node;
-    use super::SizeItem;
+    use std::path::Path;
+    use std::time::SystemTime;
+    use disk_usage::{NodeId, Tree};
+    use disk_usage::query::breakdown::FileGroup;
+    use super::{Units, ViewOptions};
+    use super::duplicates::DuplicateGroup;
+    use super::sunburst::{self, Sector};
+    use super::treemap::{squarify, Rect};
+    use super::{SizeItem, SunburstSegment, TreemapRect};
 
-    pub(super) fn node_to_size_items(node: Arc<Node>) -> Vec<SizeItem> {
-        return node_ref_to_size_items(&node);
-    }
+    // relative to the height of the treemap:
+    const TREEMAP_PADDING: f64 = 0.004;
+    const TREEMAP_HEADER: f64 = 0.035;
 
-    pub(super) fn node_ref_to_size_items(node: &Node) -> Vec<SizeItem> {
-        let subnodes: &Vec<Arc<Node>> = match node {
-            Node::File { name, size_on_disk: _, size_real } => return vec![
+    pub(super) fn node_to_size_items(tree: &Tree, node: NodeId, view: &ViewOptions, with_up_item: bool) -> Vec<SizeItem> {
+        if !tree.is_dir(node) {
+            let modified = tree.newest_modified(node);
+            return vec![
                 SizeItem {
-                    name: name.into(),
-                    size_string: readable_size(size_real).into(),
+                    name: tree.name(node).as_ref().into(),
+                    size_string: readable_size(&tree.size_real(node), view.units).into(),
+                    count_string: "".into(),
+                    age_string: readable_age(&modified, &modified).into(),
                     relative_real_size: 1_f32,
                     relative_disk_size: 1_f32,
                     is_file: true,
-                }],
-            Node::Dir { name: _, nodes } => nodes,
-        };
-        return subnodes_to_size_items(subnodes);
+                }];
+        }
+        subnodes_to_size_items(tree, node, view, with_up_item)
     }
 
-    pub(super) fn subnodes_to_size_items(subnodes: &Vec<Arc<Node>>) -> Vec<SizeItem> {
+    // the ".." item goes first, so that even empty directories have something to step into:
+    pub(super) fn subnodes_to_size_items(tree: &Tree, node: NodeId, view: &ViewOptions, with_up_item: bool) -> Vec<SizeItem> {
+        let subnodes = view.children(tree, node);
+        let metric = view.metric;
         // using 1 as default to avoid division by 0:
-        let max_disk_size = subnodes.iter().map(|i| i.size_on_disk()).max().unwrap_or(1);
-        let max_real_size = subnodes.iter().map(|i| i.size_real()).max().unwrap_or(1);
-        return subnodes.iter()
-            .map(|node| node_to_size_item(node, &max_real_size, &max_disk_size))
-            .collect();
+        let max_disk_size = subnodes.iter().map(|i| metric.value(tree, *i)).max().unwrap_or(1);
+        let max_real_size = subnodes.iter().map(|i| tree.size_real(*i)).max().unwrap_or(1);
+        let up_item = SizeItem {
+            name: "..".into(),
+            size_string: "".into(),
+            count_string: "".into(),
+            age_string: "".into(),
+            relative_real_size: 0_f32,
+            relative_disk_size: 0_f32,
+            is_file: false,
+        };
+        let up_items = if with_up_item { vec![up_item] } else { Vec::new() };
+        up_items.into_iter()
+            .chain(subnodes.iter().map(|node| node_to_size_item(tree, *node, view, &max_real_size, &max_disk_size)))
+            .collect()
     }
 
-    fn node_to_size_item(node: &Node, max_real_size: &u64, max_disk_size: &u64) -> SizeItem {
-        let size_real = node.size_real();
-        let size_on_disk = node.size_on_disk();
-        let readable_size = format!("{} ({} on disk)", readable_size(&size_real), readable_size(&size_on_disk));
+    pub(super) fn groups_to_size_items(tree: &Tree, groups: &[FileGroup], units: Units) -> Vec<SizeItem> {
+        let max_disk_size = groups.iter().map(|g| g.size_on_disk(tree)).max().unwrap_or(1);
+        let max_real_size = groups.iter().map(|g| g.size_real(tree)).max().unwrap_or(1);
+        groups.iter()
+            .map(|group| {
+                let size_real = group.size_real(tree);
+                let size_on_disk = group.size_on_disk(tree);
+                SizeItem {
+                    name: group.name.clone().into(),
+                    size_string: format!("{} ({} on disk)", readable_size(&size_real, units), readable_size(&size_on_disk, units)).into(),
+                    count_string: format!("{} files", group.files.len()).into(),
+                    age_string: "".into(),
+                    relative_real_size: (size_real as f64 / max_real_size as f64) as f32,
+                    relative_disk_size: (size_on_disk as f64 / max_disk_size as f64) as f32,
+                    // groups can be stepped into, so they are shown as folders:
+                    is_file: false,
+                }
+            })
+            .collect()
+    }
+
+    pub(super) fn duplicate_groups_to_size_items(tree: &Tree, groups: &[DuplicateGroup], units: Units) -> Vec<SizeItem> {
+        let max_reclaimable = groups.iter().map(|g| g.reclaimable(tree)).max().unwrap_or(1);
+        groups.iter()
+            .map(|group| SizeItem {
+                name: tree.name(group.files[0]).as_ref().into(),
+                size_string: format!("{} reclaimable, {} each", readable_size(&group.reclaimable(tree), units), readable_size(&group.size_real(tree), units)).into(),
+                count_string: format!("{} copies", group.files.len()).into(),
+                age_string: "".into(),
+                relative_real_size: (group.reclaimable(tree) as f64 / max_reclaimable as f64) as f32,
+                relative_disk_size: (group.reclaimable(tree) as f64 / max_reclaimable as f64) as f32,
+                is_file: false,
+            })
+            .collect()
+    }
+
+    // files are named by their paths relative to the base they were found below:
+    pub(super) fn found_files_to_size_items(tree: &Tree, base: NodeId, files: &[NodeId], units: Units) -> Vec<SizeItem> {
+        let view = ViewOptions { units, ..ViewOptions::by_disk_usage() };
+        let max_disk_size = files.iter().map(|f| tree.size_on_disk(*f)).max().unwrap_or(1);
+        let max_real_size = files.iter().map(|f| tree.size_real(*f)).max().unwrap_or(1);
+        files.iter()
+            .map(|file| SizeItem {
+                name: tree.relative_path(base, *file).into(),
+                ..node_to_size_item(tree, *file, &view, &max_real_size, &max_disk_size)
+            })
+            .collect()
+    }
+
+    // lays out children of the node and, inside directories, their children as well;
+    // laid out with the height of 1 and then scaled to 0..1 in both directions:
+    pub(super) fn node_to_treemap_rects(tree: &Tree, node: NodeId, view: &ViewOptions, aspect: f64) -> Vec<TreemapRect> {
+        let children = view.children(tree, node);
+        let bounds = Rect { x: 0.0, y: 0.0, width: aspect, height: 1.0 };
+        let mut treemap_rects: Vec<TreemapRect> = Vec::new();
+        let rects = squarify(&children.iter().map(|n| view.metric.value(tree, *n)).collect::<Vec<u64>>(), bounds);
+        for (index, (child, rect)) in children.iter().zip(rects).enumerate() {
+            treemap_rects.push(treemap_rect(tree, *child, &rect, aspect, 0, index, view.units));
+            if !tree.is_dir(*child) {
+                continue;
+            }
+            let grandchildren = view.nested_children(tree, *child);
+            let inner = match rect.inset(TREEMAP_PADDING, TREEMAP_HEADER, TREEMAP_PADDING, TREEMAP_PADDING) {
+                Some(r) => r,
+                None => continue,
+            };
+            let inner_rects = squarify(&grandchildren.iter().map(|n| view.metric.value(tree, *n)).collect::<Vec<u64>>(), inner);
+            for (grandchild, inner_rect) in grandchildren.iter().zip(inner_rects) {
+                treemap_rects.push(treemap_rect(tree, *grandchild, &inner_rect, aspect, 1, index, view.units));
+            }
+        }
+        treemap_rects.retain(|r| r.width > 0.0 && r.height > 0.0);
+        treemap_rects
+    }
+
+    // index is the one of the item in the list the rectangle belongs to:
+    fn treemap_rect(tree: &Tree, node: NodeId, rect: &Rect, aspect: f64, depth: i32, index: usize, units: Units) -> TreemapRect {
+        TreemapRect {
+            x: (rect.x / aspect) as f32,
+            y: rect.y as f32,
+            width: (rect.width / aspect) as f32,
+            height: rect.height as f32,
+            name: tree.name(node).as_ref().into(),
+            size_string: readable_size(&tree.size_on_disk(node), units).into(),
+            is_file: !tree.is_dir(node),
+            depth,
+            index: index as i32,
+        }
+    }
+
+    // rings around the current node, each segment sized by the active metric:
+    pub(super) fn node_to_sunburst_segments(tree: &Tree, node: NodeId, view: &ViewOptions) -> Vec<SunburstSegment> {
+        let mut segments: Vec<SunburstSegment> = Vec::new();
+        add_sunburst_ring(tree, &view.children(tree, node), view, 1, 0.0, 360.0, None, &mut segments);
+        segments
+    }
+
+    // index is the one of the list item the segments belong to, known once below the first ring:
+    #[allow(clippy::too_many_arguments)]
+    fn add_sunburst_ring(
+        tree: &Tree,
+        nodes: &[NodeId],
+        view: &ViewOptions,
+        depth: usize,
+        start_angle: f64,
+        end_angle: f64,
+        index: Option<usize>,
+        segments: &mut Vec<SunburstSegment>,
+    ) {
+        let total: u64 = nodes.iter().map(|n| view.metric.value(tree, *n)).sum();
+        if total == 0 {
+            return;
+        }
+        let mut angle = start_angle;
+        for (i, node) in nodes.iter().enumerate() {
+            let sweep = (end_angle - start_angle) * view.metric.value(tree, *node) as f64 / total as f64;
+            let sector = Sector::ring(depth, angle, angle + sweep);
+            let list_index = index.unwrap_or(i);
+            segments.push(SunburstSegment {
+                commands: sector.commands().into(),
+                name: tree.name(*node).as_ref().into(),
+                size_string: readable_size(&tree.size_on_disk(*node), view.units).into(),
+                is_file: !tree.is_dir(*node),
+                depth: depth as i32,
+                index: list_index as i32,
+                start_angle: sector.start_angle as f32,
+                end_angle: sector.end_angle as f32,
+                inner_radius: sector.inner_radius as f32,
+                outer_radius: sector.outer_radius as f32,
+            });
+            if depth < sunburst::LEVELS && sweep >= sunburst::MIN_SPLIT_ANGLE && tree.is_dir(*node) {
+                add_sunburst_ring(tree, &view.nested_children(tree, *node), view, depth + 1, angle, angle + sweep, Some(list_index), segments);
+            }
+            angle += sweep;
+        }
+    }
+
+    // the bar is drawn from relative_disk_size, so it follows the active metric:
+    fn node_to_size_item(tree: &Tree, node: NodeId, view: &ViewOptions, max_real_size: &u64, max_metric_value: &u64) -> SizeItem {
+        let size_real = tree.size_real(node);
+        let size_on_disk = tree.size_on_disk(node);
+        let readable_size = format!("{} ({} on disk)", readable_size(&size_real, view.units), readable_size(&size_on_disk, view.units));
+        let count_string = if tree.is_dir(node) {
+            readable_counts(tree.file_count(node), tree.dir_count(node))
+        } else {
+            "".to_string()
+        };
         SizeItem {
-            name: node.name().into(),
+            name: tree.name(node).as_ref().into(),
             size_string: readable_size.into(),
+            count_string: count_string.into(),
+            age_string: readable_age(&tree.newest_modified(node), &tree.oldest_modified(node)).into(),
             relative_real_size: (size_real as f64 / *max_real_size as f64) as f32,
-            relative_disk_size: (size_on_disk as f64 / *max_disk_size as f64) as f32,
-            is_file: match node {
-                Node::Dir { name: _, nodes: _ } => false,
-                Node::File { name: _, size_on_disk: _, size_real: _ } => true
-            },
+            relative_disk_size: (view.metric.value(tree, node) as f64 / *max_metric_value as f64) as f32,
+            is_file: !tree.is_dir(node),
+        }
+    }
+
+    // drawn like a directory, the bar showing how full the volume is:
+    pub(super) fn volume_to_size_item(root: &Path, label: Option<String>, space: Option<(u64, u64)>, units: Units) -> SizeItem {
+        let name = match &label {
+            Some(l) if !l.is_empty() => format!("{} {}", root.display(), l),
+            _ => root.display().to_string(),
+        };
+        let (size_string, count_string, relative_used) = match space {
+            Some((free, total)) if total > 0 => (
+                format!("{} used of {}", readable_size(&(total - free.min(total)), units), readable_size(&total, units)),
+                format!("{} free", readable_size(&free, units)),
+                (total - free.min(total)) as f32 / total as f32,
+            ),
+            _ => ("not ready".to_string(), "".to_string(), 0.0),
+        };
+        SizeItem {
+            name: name.into(),
+            size_string: size_string.into(),
+            count_string: count_string.into(),
+            age_string: "".into(),
+            relative_real_size: relative_used,
+            relative_disk_size: relative_used,
+            is_file: false,
+        }
+    }
+
+    fn readable_counts(files: u64, dirs: u64) -> String {
+        format!("{} files, {} dirs, {} entries", files, dirs, files + dirs)
+    }
+
+    // for files newest and oldest are the same, so only one of them is shown:
+    fn readable_age(newest: &Option<SystemTime>, oldest: &Option<SystemTime>) -> String {
+        let now = SystemTime::now();
+        match (newest, oldest) {
+            (Some(n), Some(o)) if n != o => format!(
+                "modified {}, oldest {}", readable_days_ago(&now, n), readable_days_ago(&now, o)),
+            (Some(n), _) => format!("modified {}", readable_days_ago(&now, n)),
+            _ => "".to_string(),
+        }
+    }
+
+    fn readable_days_ago(now: &SystemTime, time: &SystemTime) -> String {
+        // timestamps in the future are treated as just modified:
+        let days = now.duration_since(*time)
+            .map(|d| d.as_secs() / (24 * 60 * 60))
+            .unwrap_or(0);
+        match days {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            d => format!("{} days ago", d),
         }
     }
 
-    fn readable_size(input_size: &u64) -> String {
+    // the remainder is kept in thousandths of the unit, whatever its base:
+    pub(super) fn readable_size(input_size: &u64, units: Units) -> String {
         let mut size: u64 = *input_size;
         let mut size_remainder: u64 = 0;
-        let units = vec!["B", "kB", "MB", "GB", "TB", "PB"];
-        for unit in units {
+        for unit in units.names() {
             if size < 1000 {
                 // keep size at 3 digits:
                 if size >= 100 {
@@ -349,17 +1189,17 @@ mod ui {
                 if size_remainder / 10 == 0 {
                     return format!("{} {}", size, unit);
                 }
-                size_remainder = size_remainder / 10;
+                size_remainder /= 10;
                 if size_remainder < 10 {
                     return format!("{}.0{} {}", size, size_remainder, unit);
                 }
                 return format!("{}.{} {}", size, size_remainder, unit);
             }
-            size_remainder = size % 1000;
-            size = size / 1000;
+            size_remainder = size % units.base() * 1000 / units.base();
+            size /= units.base();
         }
         eprintln!("overflown any reasonable units: {}", input_size);
-        return format!("{} {}", input_size, "B");
+        format!("{} {}", input_size, "B")
         // TODO: use float formatting but strip trailing zeros:
         // let mut size: f64 = self.size() as f64;
         // let units = vec!["B", "kB", "MB", "GB", "TB"];
@@ -375,16 +1215,213 @@ mod ui {
 
     #[cfg(test)]
     mod tests {
+        use std::time::Duration;
         use super::*;
 
         #[test]
         fn correct_kilobytes_of_sector() {
-            assert_eq!("4.09 kB", readable_size(&4096));
+            assert_eq!("4.09 kB", readable_size(&4096, Units::Decimal));
         }
 
         #[test]
         fn correct_number_of_digits() {
-            assert_eq!("6.72 kB", readable_size(&6725));
+            assert_eq!("6.72 kB", readable_size(&6725, Units::Decimal));
         }
+
+        #[test]
+        fn binary_units_step_by_1024() {
+            assert_eq!("4 KiB", readable_size(&4096, Units::Binary));
+            assert_eq!("1.50 KiB", readable_size(&1536, Units::Binary));
+            assert_eq!("0.99 MiB", readable_size(&1_040_000, Units::Binary));
+        }
+
+        #[test]
+        fn age_in_whole_days() {
+            let now = SystemTime::now();
+            let time = now - Duration::from_secs(3 * 24 * 60 * 60 + 5);
+            assert_eq!("3 days ago", readable_days_ago(&now, &time));
+            assert_eq!("today", readable_days_ago(&now, &now));
+        }
+
+        #[test]
+        fn entries_sum_files_and_dirs() {
+            assert_eq!("12 files, 3 dirs, 15 entries", readable_counts(12, 3));
+        }
+
+        #[test]
+        fn volumes_show_used_and_free_space() {
+            let item = volume_to_size_item(Path::new("D:\\"), Some("Data".to_string()), Some((250_000, 1_000_000)), Units::Decimal);
+            assert_eq!("D:\\ Data", item.name.as_str());
+            assert_eq!("750 kB used of 1 MB", item.size_string.as_str());
+            assert_eq!("250 kB free", item.count_string.as_str());
+            assert_eq!(0.75, item.relative_disk_size);
+            assert_eq!("not ready", volume_to_size_item(Path::new("E:\\"), None, None, Units::Decimal).size_string.as_str());
+        }
+    }
+}
+
+// coordinates are relative to the size of the chart, from 0 to 1;
+// returns index of the segment under them, or -1 if there is none:
+pub fn sunburst_segment_at<I: Iterator<Item = SunburstSegment>>(segments: I, x: f32, y: f32) -> i32 {
+    let x = x as f64 * sunburst::VIEWBOX_SIZE;
+    let y = y as f64 * sunburst::VIEWBOX_SIZE;
+    for (i, segment) in segments.enumerate() {
+        let sector = sunburst::Sector {
+            start_angle: segment.start_angle as f64,
+            end_angle: segment.end_angle as f64,
+            inner_radius: segment.inner_radius as f64,
+            outer_radius: segment.outer_radius as f64,
+        };
+        if sector.contains(x, y) {
+            return i as i32;
+        }
+    }
+    -1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_tree::{build, dir, file, find, TestNode};
+
+    fn app_state_with(root: TestNode) -> AppState {
+        let app_state = AppState::new();
+        app_state.state.lock().unwrap().tree = Arc::new(build(root));
+        app_state
+    }
+
+    #[test]
+    fn duplicates_are_not_looked_for_in_listed_trees() {
+        let app_state = AppState::new();
+        app_state.show_tree(build(dir("root", vec![file("a", 1), file("b", 1)])));
+        assert!(app_state.open_duplicates(0).is_none());
+    }
+
+    #[test]
+    fn removed_files_are_dropped_from_tree_keeping_navigation() {
+        let tree = build(dir("root", vec![
+            dir("a", vec![dir("b", vec![file("dup", 30), file("other", 5)]), file("keep", 7)]),
+        ]));
+        let outer = find(&tree, "a");
+        let dup = find(&tree, "a/b/dup");
+        let mut state = RootAndNavigation {
+            tree: Arc::new(tree),
+            navigation: vec![outer],
+            view: ViewOptions::by_disk_usage(),
+            flat_view: None,
+            flat_view_return_cursor: 0,
+            pending_action: None,
+            back: Vec::new(),
+            forward: Vec::new(),
+            on_file_system: true,
+            volumes: Vec::new(),
+        };
+        state.remove_files(&[dup]);
+
+        assert_eq!(12, state.tree.size_real(state.tree.root()));
+        assert_eq!(12, state.tree.size_real(state.current_node()));
+        assert_eq!(outer, state.current_node());
+        // "keep" is now bigger than what is left in "b":
+        assert_eq!("keep", state.tree.name(state.tree.children(outer)[0]));
+    }
+
+    #[test]
+    fn up_item_shifts_list_indexes_below_the_root() {
+        let app_state = app_state_with(dir("root", vec![
+            dir("big", vec![dir("empty", vec![]), file("f", 5)]),
+            dir("small", vec![file("g", 1)]),
+        ]));
+        let (_, items) = app_state.step_into(0).unwrap();
+        assert_eq!(vec!["..", "f", "empty"], items.iter().map(|i| i.name.to_string()).collect::<Vec<String>>());
+        // "f" is the first subnode, but the second item:
+        let (_, items) = app_state.step_into(2).unwrap();
+        assert_eq!(1, items.len());
+        let (cursor, _) = app_state.step_into(0).unwrap();
+        assert_eq!(2, cursor);
+        let (cursor, items) = app_state.step_into(0).unwrap();
+        assert_eq!(0, cursor);
+        assert_eq!("big", items[0].name.to_string());
+    }
+
+    #[test]
+    fn stepping_out_several_levels_puts_cursor_on_the_directory_left() {
+        let app_state = app_state_with(dir("root", vec![
+            file("f", 50),
+            dir("a", vec![dir("b", vec![dir("c", vec![])])]),
+        ]));
+        app_state.step_into(1).unwrap();
+        app_state.step_into(1).unwrap();
+        app_state.step_into(1).unwrap();
+        let (cursor, items) = app_state.step_out_to(0).unwrap();
+        assert_eq!(1, cursor);
+        assert_eq!("a", items[1].name.to_string());
+        assert!(app_state.step_out_to(0).is_none());
+        assert!(app_state.step_out_to(1).is_none());
+    }
+
+    #[test]
+    fn back_and_forward_return_to_visited_locations() {
+        let app_state = app_state_with(dir("root", vec![
+            dir("a", vec![dir("deep", vec![file("f", 1)])]),
+            dir("b", vec![]),
+        ]));
+        app_state.step_into(0).unwrap();
+        app_state.step_into(1).unwrap();
+        app_state.step_out_to(0).unwrap();
+        app_state.step_into(1).unwrap();
+        // back from "b" is the root, with the cursor on "b":
+        assert_eq!(1, app_state.go_back().unwrap().0);
+        let (_, items) = app_state.go_back().unwrap();
+        assert_eq!(vec!["..", "f"], items.iter().map(|i| i.name.to_string()).collect::<Vec<String>>());
+        app_state.go_forward().unwrap();
+        let (_, items) = app_state.go_forward().unwrap();
+        assert_eq!(vec![".."], items.iter().map(|i| i.name.to_string()).collect::<Vec<String>>());
+        assert!(app_state.go_forward().is_none());
+    }
+
+    #[test]
+    fn history_keeps_only_the_latest_locations() {
+        let root = build(dir("root", vec![])).root();
+        let mut history = Vec::new();
+        for i in 0..HISTORY_SIZE + 5 {
+            push_to_history(&mut history, vec![root; i]);
+        }
+        assert_eq!(HISTORY_SIZE, history.len());
+        assert_eq!(5, history[0].len());
+    }
+
+    #[test]
+    fn cursor_returns_to_the_same_node_among_lookalikes() {
+        let twin = || dir("twin", vec![file("f", 10)]);
+        let app_state = app_state_with(dir("root", vec![twin(), twin(), twin()]));
+        app_state.step_into(2).unwrap();
+        assert_eq!(2, app_state.step_out().unwrap().0);
+        app_state.step_into(1).unwrap();
+        assert_eq!(1, app_state.step_out().unwrap().0);
+    }
+
+    #[test]
+    fn several_roots_are_listed_below_a_synthetic_root() {
+        let app_state = app_state_with(dir("", vec![
+            dir("/var", vec![dir("log", vec![file("syslog", 9)])]),
+            dir("/home", vec![file("notes.txt", 1)]),
+        ]));
+        assert_eq!(vec!["2 roots"], app_state.breadcrumbs());
+
+        let (_, items) = app_state.step_into(0).unwrap();
+        assert_eq!("log", items[1].name.as_str());
+        assert_eq!(vec!["2 roots", "/var"], app_state.breadcrumbs());
+        assert_eq!(format!("/var{}", std::path::MAIN_SEPARATOR), app_state.current_path_text());
+    }
+
+    #[test]
+    fn nodes_outside_of_the_list_are_not_found() {
+        let app_state = AppState::new();
+        let tree = build(dir("root", vec![dir("a", vec![file("same", 10)]), file("same", 10)]));
+        let listed = find(&tree, "same");
+        let lookalike = find(&tree, "a/same");
+        let view = ViewOptions::by_disk_usage();
+        assert_eq!(Some(1), app_state.index_of_subnode_in_node(&tree, listed, tree.root(), &view));
+        assert_eq!(None, app_state.index_of_subnode_in_node(&tree, lookalike, tree.root(), &view));
     }
 }
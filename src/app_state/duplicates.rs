@@ -0,0 +1,352 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use disk_usage::{volume, NodeId, Tree};
+
+// beginning of the file hashed first to cheaply rule out most of same-size candidates:
+const PARTIAL_HASH_SIZE: u64 = 4096;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(super) struct DuplicateGroup {
+    pub(super) files: Vec<NodeId>,
+    // files hard linked to each other take the space only once, so they count as a single copy:
+    pub(super) copies: usize,
+}
+
+impl DuplicateGroup {
+    pub(super) fn size_real(&self, tree: &Tree) -> u64 {
+        tree.size_real(self.files[0])
+    }
+
+    // everything except a single copy can be reclaimed:
+    // files replaced with hard links no longer take space of their own, so the biggest one tells:
+    pub(super) fn reclaimable(&self, tree: &Tree) -> u64 {
+        let size_on_disk = self.files.iter().map(|f| tree.size_on_disk(*f)).max().unwrap_or(0);
+        size_on_disk * (self.copies as u64).saturating_sub(1)
+    }
+}
+
+pub(super) struct Duplicates {
+    // directory the relative paths of found files start from:
+    pub(super) base: PathBuf,
+    pub(super) base_node: NodeId,
+    // sorted by reclaimable space, biggest first:
+    pub(super) groups: Vec<DuplicateGroup>,
+    // files which could not be read to be compared:
+    pub(super) unreadable: Vec<PathBuf>,
+    // index of the group which files are listed, if drilled down:
+    pub(super) opened: Option<usize>,
+}
+
+impl Duplicates {
+    pub(super) fn path_of(&self, tree: &Tree, file: NodeId) -> PathBuf {
+        self.base.join(tree.relative_path_buf(self.base_node, file))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DuplicatesAction {
+    Delete,
+    HardLink,
+}
+
+impl DuplicatesAction {
+    pub(super) fn verb(&self) -> &'static str {
+        match self {
+            DuplicatesAction::Delete => "Delete",
+            DuplicatesAction::HardLink => "Replace with hard links",
+        }
+    }
+}
+
+// same-size candidates are confirmed by hashing the beginning and then the whole content:
+pub(super) fn find_duplicates(tree: &Tree, base: PathBuf, base_node: NodeId, files: Vec<NodeId>) -> Duplicates {
+    let mut by_size: HashMap<u64, Vec<NodeId>> = HashMap::new();
+    // members of archives cannot be read or replaced one by one:
+    for file in files.into_iter().filter(|f| !tree.is_in_archive(*f)) {
+        let size = tree.size_real(file);
+        // empty files are all equal, but there is nothing to reclaim:
+        if size > 0 {
+            by_size.entry(size).or_default().push(file);
+        }
+    }
+    let candidates: Vec<Vec<NodeId>> = by_size.into_values()
+        .filter(|g| g.len() > 1)
+        .collect();
+    let mut unreadable: Vec<PathBuf> = Vec::new();
+    let candidates = split_by_hash(tree, &base, base_node, candidates, Some(PARTIAL_HASH_SIZE), &mut unreadable);
+    // files not bigger than the partial hash were already hashed whole:
+    let (hashed_whole, hashed_partially): (Vec<Vec<NodeId>>, Vec<Vec<NodeId>>) = candidates.into_iter()
+        .partition(|g| tree.size_real(g[0]) <= PARTIAL_HASH_SIZE);
+    let mut groups: Vec<DuplicateGroup> = hashed_whole.into_iter()
+        .chain(split_by_hash(tree, &base, base_node, hashed_partially, None, &mut unreadable))
+        .map(|mut files| {
+            files.sort_by_key(|f| tree.relative_path(base_node, *f));
+            let copies = count_copies(files.iter().map(|f| base.join(tree.relative_path_buf(base_node, *f))));
+            DuplicateGroup { files, copies }
+        })
+        // names of a single file linked several times, nothing to reclaim:
+        .filter(|g| g.copies > 1)
+        .collect();
+    groups.sort_by_key(|g| Reverse(g.reclaimable(tree)));
+    Duplicates { base, base_node, groups, unreadable, opened: None }
+}
+
+// the file at the kept index stays, the others are deleted or replaced;
+// returns indexes of files which were deleted or replaced:
+pub(super) fn apply_action(paths: &[PathBuf], keep: usize, action: DuplicatesAction) -> Vec<usize> {
+    let kept = &paths[keep];
+    let mut done: Vec<usize> = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        if i == keep {
+            continue;
+        }
+        // hashes could collide and files could change since the scan, so checking again byte by byte:
+        let result = match files_equal(kept, path) {
+            Ok(true) => match action {
+                DuplicatesAction::Delete => fs::remove_file(path),
+                DuplicatesAction::HardLink => replace_with_hard_link(kept, path),
+            },
+            Ok(false) => {
+                eprintln!("File {:?} is no longer the same as {:?}, skipping.", path, kept);
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => done.push(i),
+            Err(e) => eprintln!("Failed to process duplicate: {:?}, because of: {:?}", path, e),
+        }
+    }
+    done
+}
+
+// files which cannot be identified are counted as separate copies:
+pub(super) fn count_copies<I: Iterator<Item = PathBuf>>(paths: I) -> usize {
+    let mut ids: HashSet<(u64, u64)> = HashSet::new();
+    let mut unknown = 0;
+    for path in paths {
+        match volume::file_id(&path) {
+            Ok(id) => {
+                ids.insert(id);
+            }
+            Err(_) => unknown += 1,
+        }
+    }
+    ids.len() + unknown
+}
+
+// unreadable files cannot be confirmed as duplicates, they are left out and told about:
+fn split_by_hash(
+    tree: &Tree,
+    base: &Path,
+    base_node: NodeId,
+    groups: Vec<Vec<NodeId>>,
+    limit: Option<u64>,
+    unreadable: &mut Vec<PathBuf>,
+) -> Vec<Vec<NodeId>> {
+    let files: Vec<(usize, NodeId)> = groups.into_iter()
+        .enumerate()
+        .flat_map(|(i, files)| files.into_iter().map(move |f| (i, f)))
+        .collect();
+    let paths: Vec<PathBuf> = files.iter()
+        .map(|(_, f)| base.join(tree.relative_path_buf(base_node, *f)))
+        .collect();
+    let hashes = hash_files_in_parallel(&paths, limit);
+    let mut by_hash: HashMap<(usize, u64), Vec<NodeId>> = HashMap::new();
+    for (((group, file), hash), path) in files.into_iter().zip(hashes).zip(paths) {
+        match hash {
+            Some(h) => by_hash.entry((group, h)).or_default().push(file),
+            None => unreadable.push(path),
+        }
+    }
+    by_hash.into_values()
+        .filter(|g| g.len() > 1)
+        .collect()
+}
+
+fn hash_files_in_parallel(paths: &[PathBuf], limit: Option<u64>) -> Vec<Option<u64>> {
+    let hashes: Mutex<Vec<Option<u64>>> = Mutex::new(vec![None; paths.len()]);
+    let next = AtomicUsize::new(0);
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= paths.len() {
+                    break;
+                }
+                let hash = hash_file(&paths[i], limit).ok();
+                hashes.lock()
+                    .expect("Failed to acquire mutex lock on hashes")[i] = hash;
+            });
+        }
+    });
+    hashes.into_inner()
+        .expect("Failed to take hashes out of mutex")
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> io::Result<u64> {
+    let mut reader = File::open(path)?.take(limit.unwrap_or(u64::MAX));
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0_u8; READ_BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..read]);
+    }
+}
+
+fn files_equal(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut reader_a = File::open(a)?;
+    let mut reader_b = File::open(b)?;
+    let mut buffer_a = vec![0_u8; READ_BUFFER_SIZE];
+    let mut buffer_b = vec![0_u8; READ_BUFFER_SIZE];
+    loop {
+        let read_a = read_full(&mut reader_a, &mut buffer_a)?;
+        let read_b = read_full(&mut reader_b, &mut buffer_b)?;
+        if buffer_a[..read_a] != buffer_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// fills the buffer unless the end of file is reached, so both files are compared in same chunks:
+fn read_full(reader: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buffer.len() {
+        let read = reader.read(&mut buffer[total..])?;
+        if read == 0 {
+            break;
+        }
+        total += read;
+    }
+    Ok(total)
+}
+
+fn replace_with_hard_link(original: &Path, duplicate: &Path) -> io::Result<()> {
+    let mut temporary = duplicate.as_os_str().to_os_string();
+    temporary.push(".disk_usage_link");
+    let temporary = PathBuf::from(temporary);
+    fs::hard_link(original, &temporary)?;
+    fs::rename(&temporary, duplicate).inspect_err(|_| {
+        let _ = fs::remove_file(&temporary);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::ffi::OsStr;
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("disk_usage_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Failed to create test dir");
+        dir
+    }
+
+    fn add_file(tree: &mut Tree, dir: &Path, name: &str, content: &[u8]) -> NodeId {
+        fs::write(dir.join(name), content).expect("Failed to write test file");
+        let root = tree.root();
+        tree.add_file(root, OsStr::new(name), content.len() as u64, content.len() as u64, None, None)
+    }
+
+    #[test]
+    fn same_size_files_are_confirmed_by_content() {
+        let dir = test_dir("duplicates");
+        // same beginning, so only the full hash tells them apart:
+        let content = vec![7_u8; PARTIAL_HASH_SIZE as usize * 2];
+        let mut different = content.clone();
+        different[PARTIAL_HASH_SIZE as usize + 1] = 8;
+        let mut tree = Tree::new(&dir);
+        let files = vec![
+            add_file(&mut tree, &dir, "a", &content),
+            add_file(&mut tree, &dir, "b", &different),
+            add_file(&mut tree, &dir, "c", &content),
+            add_file(&mut tree, &dir, "gone", &content),
+            add_file(&mut tree, &dir, "empty1", b""),
+            add_file(&mut tree, &dir, "empty2", b""),
+        ];
+        fs::remove_file(dir.join("gone")).expect("Failed to remove test file");
+        tree.set_children(tree.root(), files.clone());
+        let duplicates = find_duplicates(&tree, dir.clone(), tree.root(), files);
+        fs::remove_dir_all(&dir).expect("Failed to remove test dir");
+
+        let groups = &duplicates.groups;
+        assert_eq!(1, groups.len());
+        let names: Vec<Cow<str>> = groups[0].files.iter().map(|f| tree.name(*f)).collect();
+        assert_eq!(vec!["a", "c"], names);
+        assert_eq!(PARTIAL_HASH_SIZE * 2, groups[0].reclaimable(&tree));
+        assert_eq!(vec![dir.join("gone")], duplicates.unreadable);
+    }
+
+    #[test]
+    fn hard_linked_files_are_a_single_copy() {
+        let dir = test_dir("duplicates_linked");
+        let mut tree = Tree::new(Path::new("dir"));
+        let files = vec![
+            add_file(&mut tree, &dir, "a", b"same"),
+            add_file(&mut tree, &dir, "b", b"same"),
+        ];
+        fs::hard_link(dir.join("a"), dir.join("a_link")).expect("Failed to link test file");
+        let root = tree.root();
+        let linked = tree.add_file(root, OsStr::new("a_link"), 4, 4, None, None);
+        let mut all = files.clone();
+        all.push(linked);
+        tree.set_children(root, all.clone());
+        let groups = find_duplicates(&tree, dir.clone(), root, all).groups;
+        // only names of the same file are left:
+        fs::remove_file(dir.join("b")).expect("Failed to remove test file");
+        let only_links = find_duplicates(&tree, dir.clone(), root, vec![files[0], linked]).groups;
+        fs::remove_dir_all(&dir).expect("Failed to remove test dir");
+
+        assert_eq!(1, groups.len());
+        assert_eq!(3, groups[0].files.len());
+        assert_eq!(4, groups[0].reclaimable(&tree));
+        assert!(only_links.is_empty());
+    }
+    #[test]
+    fn actions_keep_one_file_and_skip_changed_ones() {
+        let dir = test_dir("duplicates_actions");
+        let paths: Vec<PathBuf> = ["keep", "same", "changed"].iter().map(|n| dir.join(n)).collect();
+        for (path, content) in paths.iter().zip([&b"content"[..], b"content", b"contents"]) {
+            fs::write(path, content).expect("Failed to write test file");
+        }
+        assert!(files_equal(&paths[0], &paths[1]).unwrap());
+        assert!(!files_equal(&paths[0], &paths[2]).unwrap());
+
+        assert_eq!(vec![1], apply_action(&paths, 0, DuplicatesAction::HardLink));
+        fs::write(&paths[0], b"written through").expect("Failed to write test file");
+        let linked = fs::read(&paths[1]).unwrap();
+        let copies = count_copies(paths.iter().cloned());
+        assert_eq!(vec![1], apply_action(&paths, 0, DuplicatesAction::Delete));
+        let deleted = !paths[1].exists();
+        let kept = paths[0].exists() && paths[2].exists();
+        let leftovers = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).expect("Failed to remove test dir");
+
+        assert_eq!(b"written through".to_vec(), linked);
+        assert_eq!(2, copies);
+        assert!(deleted);
+        assert!(kept);
+        // no temporary link is left behind:
+        assert_eq!(2, leftovers);
+    }
+}
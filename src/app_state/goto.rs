@@ -0,0 +1,149 @@
+use std::ffi::OsStr;
+use std::path::{Component, Path, MAIN_SEPARATOR};
+
+use disk_usage::{NodeId, Tree};
+
+// resolves a typed path to the navigation leading to it, where the last node may be a file;
+// absolute paths have to be inside the scanned root, relative ones start from the current node:
+pub(super) fn resolve(tree: &Tree, navigation: &[NodeId], text: &str) -> Result<Vec<NodeId>, String> {
+    let path = Path::new(text.trim());
+    let (mut resolved, relative) = if path.has_root() {
+        match strip_root(tree, path) {
+            Some(stripped) => stripped,
+            None if tree.root_path().as_os_str().is_empty() => return Err(format!("{} is outside of the scanned directories", text.trim())),
+            None => return Err(format!("{} is outside of the scanned directory {}", text.trim(), tree.root_path().display())),
+        }
+    } else {
+        (navigation.to_vec(), path)
+    };
+    for component in relative.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                let parent = resolved.last().copied().unwrap_or(tree.root());
+                if !tree.is_dir(parent) {
+                    return Err(format!("{} is a file", tree.name(parent)));
+                }
+                match find_child(tree, parent, name) {
+                    Some(child) => resolved.push(child),
+                    None => return Err(format!("{} not found in {}", name.to_string_lossy(), tree.name(parent))),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(resolved)
+}
+
+// several roots scanned together hang below a root without a path, named by their full paths:
+fn strip_root<'a>(tree: &Tree, path: &'a Path) -> Option<(Vec<NodeId>, &'a Path)> {
+    if tree.root_path().as_os_str().is_empty() {
+        return tree.children(tree.root()).iter()
+            .find_map(|top| path.strip_prefix(tree.os_name(*top)).ok().map(|relative| (vec![*top], relative)));
+    }
+    path.strip_prefix(tree.root_path()).ok().map(|relative| (Vec::new(), relative))
+}
+
+// completes the last component of the typed path with the longest prefix shared by matching names:
+pub(super) fn complete(tree: &Tree, navigation: &[NodeId], text: &str) -> String {
+    let (head, partial) = match text.rfind(std::path::is_separator) {
+        Some(i) => text.split_at(i + 1),
+        None => ("", text),
+    };
+    let directory = match resolve(tree, navigation, head) {
+        Ok(resolved) => resolved.last().copied().unwrap_or(tree.root()),
+        Err(_) => return text.to_string(),
+    };
+    if !tree.is_dir(directory) {
+        return text.to_string();
+    }
+    let partial_lowercase = partial.to_lowercase();
+    let candidates: Vec<NodeId> = tree.children(directory).iter()
+        .copied()
+        .filter(|n| tree.name(*n).to_lowercase().starts_with(&partial_lowercase))
+        .collect();
+    if candidates.is_empty() {
+        return text.to_string();
+    }
+    let mut completion = tree.name(candidates[0]).to_string();
+    for candidate in &candidates[1..] {
+        let name = tree.name(*candidate);
+        let shared = completion.chars()
+            .zip(name.chars())
+            .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        completion.truncate(shared);
+    }
+    // typed text is kept when no name is longer than it, so the case typed by the user is not lost:
+    if completion.chars().count() <= partial.chars().count() && candidates.len() > 1 {
+        return text.to_string();
+    }
+    if candidates.len() == 1 && tree.is_dir(candidates[0]) {
+        completion.push(MAIN_SEPARATOR);
+    }
+    format!("{}{}", head, completion)
+}
+
+// exact match first, then ignoring case, as Windows paths are typed either way;
+// names which are not valid unicode can only be typed the way they are shown, escaped:
+fn find_child(tree: &Tree, dir: NodeId, name: &OsStr) -> Option<NodeId> {
+    let children = tree.children(dir);
+    let typed = name.to_string_lossy().to_lowercase();
+    children.iter()
+        .find(|n| tree.os_name(**n) == name)
+        .or_else(|| children.iter().find(|n| tree.name(**n).to_lowercase() == typed))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use super::*;
+    use super::super::test_tree::*;
+
+    fn tree() -> Tree {
+        build(dir("/data", vec![
+            dir("Documents", vec![file("notes.txt", 1), file("novel.txt", 1)]),
+            dir("Downloads", vec![]),
+            file("music.mp3", 1),
+        ]))
+    }
+
+    #[test]
+    fn relative_paths_start_from_current_node() {
+        let tree = tree();
+        let documents = resolve(&tree, &Vec::new(), "documents").unwrap();
+        assert_eq!("Documents", tree.name(documents[0]));
+        let file = resolve(&tree, &documents, "../Documents/notes.txt").unwrap();
+        assert_eq!(vec!["Documents", "notes.txt"], file.iter().map(|n| tree.name(*n)).collect::<Vec<Cow<str>>>());
+        assert!(resolve(&tree, &Vec::new(), "missing").is_err());
+        assert_eq!(file, resolve(&tree, &Vec::new(), "/data/Documents/notes.txt").unwrap());
+        assert!(resolve(&tree, &Vec::new(), "/elsewhere/Documents").is_err());
+    }
+
+    #[test]
+    fn absolute_paths_start_from_the_matching_root_of_several() {
+        let tree = build(dir("", vec![
+            dir("/var", vec![dir("log", vec![file("syslog", 1)])]),
+            dir("/home", vec![file("notes.txt", 1)]),
+        ]));
+        let log = resolve(&tree, &Vec::new(), "/var/log").unwrap();
+        assert_eq!(vec!["/var", "log"], log.iter().map(|n| tree.name(*n)).collect::<Vec<Cow<str>>>());
+        let notes = resolve(&tree, &log, "/home/notes.txt").unwrap();
+        assert_eq!(vec!["/home", "notes.txt"], notes.iter().map(|n| tree.name(*n)).collect::<Vec<Cow<str>>>());
+        assert!(resolve(&tree, &Vec::new(), "/opt").is_err());
+    }
+
+    #[test]
+    fn tab_completes_shared_prefix_and_whole_directory_names() {
+        let tree = tree();
+        assert_eq!("Do", complete(&tree, &Vec::new(), "d"));
+        assert_eq!(format!("Documents{}", MAIN_SEPARATOR), complete(&tree, &Vec::new(), "doc"));
+        assert_eq!("Documents/no", complete(&tree, &Vec::new(), "Documents/n"));
+        assert_eq!("Documents/notes.txt", complete(&tree, &Vec::new(), "Documents/not"));
+        assert_eq!("xyz", complete(&tree, &Vec::new(), "xyz"));
+    }
+}
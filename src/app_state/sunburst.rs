@@ -0,0 +1,85 @@
+use std::f64::consts::PI;
+
+// chart is drawn in a 200 x 200 viewbox, with the current node in the middle:
+pub(super) const VIEWBOX_SIZE: f64 = 200.0;
+pub(super) const CENTER_RADIUS: f64 = 20.0;
+pub(super) const LEVELS: usize = 4;
+pub(super) const RING_WIDTH: f64 = (VIEWBOX_SIZE / 2.0 - CENTER_RADIUS) / LEVELS as f64;
+// segments narrower than that are not split into deeper levels:
+pub(super) const MIN_SPLIT_ANGLE: f64 = 0.5;
+
+// angles are in degrees, clockwise from the top:
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Sector {
+    pub(super) start_angle: f64,
+    pub(super) end_angle: f64,
+    pub(super) inner_radius: f64,
+    pub(super) outer_radius: f64,
+}
+
+impl Sector {
+    pub(super) fn ring(depth: usize, start_angle: f64, end_angle: f64) -> Sector {
+        Sector {
+            start_angle,
+            end_angle,
+            inner_radius: CENTER_RADIUS + (depth - 1) as f64 * RING_WIDTH,
+            outer_radius: CENTER_RADIUS + depth as f64 * RING_WIDTH,
+        }
+    }
+
+    pub(super) fn contains(&self, x: f64, y: f64) -> bool {
+        let (radius, angle) = to_polar(x, y);
+        radius >= self.inner_radius && radius < self.outer_radius
+            && angle >= self.start_angle && angle < self.end_angle
+    }
+
+    // SVG path commands of the ring segment:
+    pub(super) fn commands(&self) -> String {
+        // arc from a point back to itself is not drawn, so full rings are left with a hairline gap:
+        let end_angle = self.end_angle.min(self.start_angle + 359.99);
+        let large_arc = if end_angle - self.start_angle > 180.0 { 1 } else { 0 };
+        let (outer_start_x, outer_start_y) = to_cartesian(self.outer_radius, self.start_angle);
+        let (outer_end_x, outer_end_y) = to_cartesian(self.outer_radius, end_angle);
+        let (inner_end_x, inner_end_y) = to_cartesian(self.inner_radius, end_angle);
+        let (inner_start_x, inner_start_y) = to_cartesian(self.inner_radius, self.start_angle);
+        format!("M {:.3} {:.3} A {:.3} {:.3} 0 {} 1 {:.3} {:.3} L {:.3} {:.3} A {:.3} {:.3} 0 {} 0 {:.3} {:.3} Z",
+                outer_start_x, outer_start_y,
+                self.outer_radius, self.outer_radius, large_arc, outer_end_x, outer_end_y,
+                inner_end_x, inner_end_y,
+                self.inner_radius, self.inner_radius, large_arc, inner_start_x, inner_start_y)
+    }
+}
+
+fn to_cartesian(radius: f64, angle: f64) -> (f64, f64) {
+    let radians = (angle - 90.0) * PI / 180.0;
+    (VIEWBOX_SIZE / 2.0 + radius * radians.cos(), VIEWBOX_SIZE / 2.0 + radius * radians.sin())
+}
+
+fn to_polar(x: f64, y: f64) -> (f64, f64) {
+    let dx = x - VIEWBOX_SIZE / 2.0;
+    let dy = y - VIEWBOX_SIZE / 2.0;
+    let angle = dy.atan2(dx) * 180.0 / PI + 90.0;
+    ((dx * dx + dy * dy).sqrt(), if angle < 0.0 { angle + 360.0 } else { angle })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_found_in_their_ring_and_angle() {
+        let top_right = Sector::ring(1, 0.0, 90.0);
+        let center = VIEWBOX_SIZE / 2.0;
+        let in_first_ring = CENTER_RADIUS + RING_WIDTH / 2.0;
+        assert!(top_right.contains(center + 5.0, center - in_first_ring));
+        assert!(!top_right.contains(center - 5.0, center - in_first_ring));
+        assert!(!top_right.contains(center + 1.0, center - 1.0));
+        assert!(!Sector::ring(2, 0.0, 90.0).contains(center + 5.0, center - in_first_ring));
+    }
+
+    #[test]
+    fn quarter_ring_path_starts_at_the_top() {
+        let commands = Sector::ring(1, 0.0, 90.0).commands();
+        assert!(commands.starts_with("M 100.000 60.000 A 40.000 40.000 0 0 1 140.000 100.000"), "{}", commands);
+    }
+}
@@ -0,0 +1,112 @@
+// squarified treemap layout, as described by Bruls, Huizing and van Wijk
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct Rect {
+    pub(super) x: f64,
+    pub(super) y: f64,
+    pub(super) width: f64,
+    pub(super) height: f64,
+}
+
+impl Rect {
+    const EMPTY: Rect = Rect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+
+    // shrinks the rectangle, leaving nothing if it is too small:
+    pub(super) fn inset(&self, left: f64, top: f64, right: f64, bottom: f64) -> Option<Rect> {
+        let width = self.width - left - right;
+        let height = self.height - top - bottom;
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        Some(Rect { x: self.x + left, y: self.y + top, width, height })
+    }
+}
+
+// values are expected to be sorted, biggest first; zero values get empty rectangles:
+pub(super) fn squarify(values: &[u64], bounds: Rect) -> Vec<Rect> {
+    let mut rects = vec![Rect::EMPTY; values.len()];
+    let total: u64 = values.iter().sum();
+    if total == 0 || bounds.width <= 0.0 || bounds.height <= 0.0 {
+        return rects;
+    }
+    let scale = bounds.width * bounds.height / total as f64;
+    let positive: Vec<usize> = (0..values.len()).filter(|i| values[*i] > 0).collect();
+    let areas: Vec<f64> = positive.iter().map(|i| values[*i] as f64 * scale).collect();
+    let mut laid_out = vec![Rect::EMPTY; areas.len()];
+    let mut free = bounds;
+    let mut row_start = 0;
+    let mut i = 0;
+    while i < areas.len() {
+        let side = free.width.min(free.height);
+        // the row grows as long as it makes its rectangles closer to squares:
+        if i == row_start || worst_ratio(&areas[row_start..=i], side) <= worst_ratio(&areas[row_start..i], side) {
+            i += 1;
+        } else {
+            free = layout_row(&areas[row_start..i], free, &mut laid_out[row_start..i]);
+            row_start = i;
+        }
+    }
+    layout_row(&areas[row_start..], free, &mut laid_out[row_start..]);
+    for (rect, i) in laid_out.into_iter().zip(positive) {
+        rects[i] = rect;
+    }
+    rects
+}
+
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side_squared = side * side;
+    let sum_squared = sum * sum;
+    (side_squared * max / sum_squared).max(sum_squared / (side_squared * min))
+}
+
+// places the row along the shorter side of the free rectangle, returning what is left free:
+fn layout_row(row: &[f64], free: Rect, out: &mut [Rect]) -> Rect {
+    let sum: f64 = row.iter().sum();
+    if free.width >= free.height {
+        let width = sum / free.height;
+        let mut y = free.y;
+        for (area, rect) in row.iter().zip(out.iter_mut()) {
+            let height = area / width;
+            *rect = Rect { x: free.x, y, width, height };
+            y += height;
+        }
+        Rect { x: free.x + width, y: free.y, width: free.width - width, height: free.height }
+    } else {
+        let height = sum / free.width;
+        let mut x = free.x;
+        for (area, rect) in row.iter().zip(out.iter_mut()) {
+            let width = area / height;
+            *rect = Rect { x, y: free.y, width, height };
+            x += width;
+        }
+        Rect { x: free.x, y: free.y + height, width: free.width, height: free.height - height }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn areas_are_proportional_to_values() {
+        let bounds = Rect { x: 0.0, y: 0.0, width: 6.0, height: 4.0 };
+        let values = [6, 6, 4, 3, 2, 2, 1];
+        let rects = squarify(&values, bounds);
+        for (value, rect) in values.iter().zip(rects.iter()) {
+            assert!((rect.width * rect.height - *value as f64).abs() < 1e-9);
+            assert!(rect.x >= 0.0 && rect.x + rect.width <= 6.0 + 1e-9);
+            assert!(rect.y >= 0.0 && rect.y + rect.height <= 4.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn zero_values_get_empty_rectangles() {
+        let bounds = Rect { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+        let rects = squarify(&[5, 0, 5], bounds);
+        assert_eq!(Rect::EMPTY, rects[1]);
+        assert!((rects[0].width * rects[0].height - 0.5).abs() < 1e-9);
+    }
+}
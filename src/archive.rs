@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Component, Path};
+use std::time::{Duration, SystemTime};
+
+use flate2::read::GzDecoder;
+
+use crate::source::ReadSeek;
+use crate::tree::{NodeId, Tree};
+
+// members of tar archives take whole blocks:
+const TAR_BLOCK: u64 = 512;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+// archive contents as a directory tree, sizes on disk being compressed sizes of members:
+pub(crate) struct Members {
+    dirs: BTreeMap<OsString, Members>,
+    files: Vec<Member>,
+}
+
+struct Member {
+    name: OsString,
+    compressed: u64,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+pub(crate) fn format_of(name: &OsStr) -> Option<Format> {
+    let name = name.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else {
+        None
+    }
+}
+
+// len is the size of the archive file, spread over members of compressed tars:
+pub(crate) fn read_members(reader: Box<dyn ReadSeek>, format: Format, len: u64) -> io::Result<Members> {
+    let mut members = Members { dirs: BTreeMap::new(), files: Vec::new() };
+    match format {
+        Format::Zip => read_zip(reader, &mut members)?,
+        Format::Tar => read_tar(reader, &mut members)?,
+        Format::TarGz => {
+            read_tar(GzDecoder::new(reader), &mut members)?;
+            let total = members.compressed();
+            if total > 0 {
+                members.scale_compressed(len, total);
+            }
+        }
+    }
+    Ok(members)
+}
+
+// the archive becomes a directory, its members are scanned already so nothing can fail here;
+// it keeps the sizes of the archive file, so that directories above add up the same as without looking inside:
+pub(crate) fn add_archive(tree: &mut Tree, parent: NodeId, name: &OsStr, members: Members, size_on_disk: u64, size_real: u64) -> NodeId {
+    let dir = tree.add_dir(parent, name);
+    tree.mark_archive(dir);
+    add_members_below(tree, dir, members);
+    tree.set_own_sizes(dir, size_on_disk, size_real);
+    dir
+}
+
+fn add_members_below(tree: &mut Tree, dir: NodeId, members: Members) {
+    let mut children: Vec<NodeId> = Vec::new();
+    for (name, sub) in members.dirs {
+        let sub_dir = tree.add_dir(dir, &name);
+        add_members_below(tree, sub_dir, sub);
+        children.push(sub_dir);
+    }
+    for file in members.files {
+        children.push(tree.add_file(dir, &file.name, file.compressed, file.size, file.modified, None));
+    }
+    tree.set_children(dir, children);
+}
+
+fn read_zip(reader: Box<dyn ReadSeek>, members: &mut Members) -> io::Result<()> {
+    let mut zip = zip::ZipArchive::new(reader)?;
+    for i in 0..zip.len() {
+        // raw access does not decompress anything:
+        let file = zip.by_index_raw(i)?;
+        // names escaping the archive with absolute paths or ".." are left out:
+        let path = match file.enclosed_name() {
+            Some(p) => p.to_path_buf(),
+            None => {
+                eprintln!("Skipping archive member with unsafe name: {:?}", file.name());
+                continue;
+            }
+        };
+        if file.is_dir() {
+            members.dir(&path);
+        } else {
+            let modified = zip_time(&file.last_modified());
+            members.add(&path, file.compressed_size(), file.size(), modified);
+        }
+    }
+    Ok(())
+}
+
+fn read_tar<R: io::Read>(reader: R, members: &mut Members) -> io::Result<()> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let header = entry.header();
+        if header.entry_type().is_dir() {
+            members.dir(&path);
+        } else {
+            let size = entry.size();
+            let modified = header.mtime().ok()
+                .map(|s| SystemTime::UNIX_EPOCH + Duration::from_secs(s));
+            members.add(&path, size.div_ceil(TAR_BLOCK) * TAR_BLOCK, size, modified);
+        }
+    }
+    Ok(())
+}
+
+impl Members {
+    fn dir(&mut self, path: &Path) -> &mut Members {
+        let mut dir = self;
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                dir = dir.dirs.entry(name.to_os_string())
+                    .or_insert_with(|| Members { dirs: BTreeMap::new(), files: Vec::new() });
+            }
+        }
+        dir
+    }
+
+    fn add(&mut self, path: &Path, compressed: u64, size: u64, modified: Option<SystemTime>) {
+        let name = match path.file_name() {
+            Some(n) => n.to_os_string(),
+            None => return,
+        };
+        let dir = match path.parent() {
+            Some(parent) => self.dir(parent),
+            None => self,
+        };
+        dir.files.push(Member { name, compressed, size, modified });
+    }
+
+    fn compressed(&self) -> u64 {
+        self.dirs.values().map(|d| d.compressed()).sum::<u64>()
+            + self.files.iter().map(|f| f.compressed).sum::<u64>()
+    }
+
+    // compressed sizes of single members of a compressed stream are unknown, so each gets its share:
+    fn scale_compressed(&mut self, len: u64, total: u64) {
+        self.dirs.values_mut().for_each(|d| d.scale_compressed(len, total));
+        self.files.iter_mut()
+            .for_each(|f| f.compressed = (f.compressed as u128 * len as u128 / total as u128) as u64);
+    }
+}
+
+// zip keeps local time without a zone, taken as UTC here:
+fn zip_time(time: &zip::DateTime) -> Option<SystemTime> {
+    let (y, m, d) = (time.year() as i64, time.month() as i64, time.day() as i64);
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    let seconds = days * 86400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64;
+    u64::try_from(seconds).ok().map(|s| SystemTime::UNIX_EPOCH + Duration::from_secs(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_are_recognized_by_extension() {
+        assert_eq!(Some(Format::Zip), format_of(OsStr::new("release.ZIP")));
+        assert_eq!(Some(Format::TarGz), format_of(OsStr::new("src.tar.gz")));
+        assert_eq!(Some(Format::TarGz), format_of(OsStr::new("src.tgz")));
+        assert_eq!(Some(Format::Tar), format_of(OsStr::new("backup.tar")));
+        assert_eq!(None, format_of(OsStr::new("notes.gz")));
+    }
+
+    #[test]
+    fn zip_times_are_converted_from_calendar_dates() {
+        let time = zip::DateTime::from_date_and_time(2000, 3, 1, 12, 30, 10).unwrap();
+        let seconds = zip_time(&time).unwrap().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(951_913_810, seconds);
+    }
+}
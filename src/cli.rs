@@ -0,0 +1,302 @@
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use disk_usage::report::{Format, Order};
+use disk_usage::{PathList, Scanner};
+
+pub const USAGE: &str = "\
+Usage: disk_usage [options] [path...]
+
+Scans the given directories, several ones side by side below one root.
+Without a path, starts by picking a volume or a folder.
+
+Options:
+  -h, --help               print this help and exit
+  -V, --version            print the version and exit
+      --exclude PATTERN    leave out entries with matching names, * and ? are wildcards; repeatable
+  -x, --one-file-system    keep directories of other volumes mounted below empty
+  -L, --follow-symlinks    scan what links point to instead of the links themselves
+      --threads N          scan with N threads, 1 by default
+      --archives           show zip, tar and tar.gz files as directories
+      --stdin              show paths listed on stdin instead of scanning
+      --null               paths on stdin are separated by NUL instead of newlines
+      --sizes              paths on stdin come with sizes in front of them
+      --import FILE        show a scan exported before instead of scanning
+      --export FILE        save the scan to FILE and exit without a window
+      --output FORMAT      print the scan as text, json or csv and exit without a window
+      --sort ORDER         rank by disk (size on disk, the default) or count (number of entries)
+";
+
+pub enum Command {
+    Help,
+    Version,
+    Run(Options),
+}
+
+pub struct Options {
+    pub paths: Vec<PathBuf>,
+    pub excluded: Vec<String>,
+    pub one_file_system: bool,
+    pub follow_symlinks: bool,
+    pub archives: bool,
+    pub threads: usize,
+    pub stdin: bool,
+    pub null: bool,
+    pub sizes: bool,
+    pub import: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+    pub output: Option<Format>,
+    pub order: Order,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            paths: Vec::new(),
+            excluded: Vec::new(),
+            one_file_system: false,
+            follow_symlinks: false,
+            archives: false,
+            threads: 1,
+            stdin: false,
+            null: false,
+            sizes: false,
+            import: None,
+            export: None,
+            output: None,
+            order: Order::DiskUsage,
+        }
+    }
+}
+
+impl Options {
+    // exporting or printing the scan is done without opening a window:
+    pub fn headless(&self) -> bool {
+        self.output.is_some() || self.export.is_some()
+    }
+
+    pub fn scanner(&self) -> Scanner {
+        let mut builder = Scanner::builder()
+            .follow_symlinks(self.follow_symlinks)
+            .one_file_system(self.one_file_system)
+            .archives(self.archives)
+            .threads(self.threads);
+        for pattern in &self.excluded {
+            builder = builder.exclude(pattern);
+        }
+        builder.build()
+    }
+
+    pub fn path_list(&self) -> Option<PathList> {
+        if !self.stdin {
+            return None;
+        }
+        let separator = if self.null { 0 } else { b'\n' };
+        Some(PathList::new(separator, self.sizes))
+    }
+}
+
+// the argument is shown below the message:
+#[derive(Debug, PartialEq)]
+pub struct ArgError {
+    pub message: String,
+    pub argument: String,
+}
+
+impl ArgError {
+    fn new(message: &str, argument: &str) -> ArgError {
+        ArgError { message: message.to_string(), argument: argument.to_string() }
+    }
+}
+
+// errors are told before the rest is parsed, so the way of reporting them is decided on the raw arguments:
+pub fn is_headless(args: &[OsString]) -> bool {
+    args.iter()
+        .filter_map(|a| a.to_str())
+        .any(|a| ["--output", "--export"].iter().any(|o| a == *o || a.starts_with(&format!("{}=", o))))
+}
+
+// options start from the defaults, which come from the config file, and are overridden by the arguments;
+// only names of options are compared as text, paths are kept as given, even when they are not valid Unicode:
+pub fn parse(args: &[OsString], defaults: Options) -> Result<Command, ArgError> {
+    let mut options = defaults;
+    let mut args = args.iter();
+    let mut only_paths = false;
+    while let Some(arg) = args.next() {
+        if only_paths || !arg.as_encoded_bytes().starts_with(b"-") {
+            options.paths.push(existing_dir(arg)?);
+            continue;
+        }
+        // values are given either as the next argument or after =:
+        let (name, inline_value) = split_value(arg)
+            .ok_or_else(|| ArgError::new("Unknown option:", &arg.to_string_lossy()))?;
+        let mut value = || inline_value.clone()
+            .or_else(|| args.next().cloned())
+            .ok_or_else(|| ArgError::new("Option needs a value:", name));
+        let mut text = || value()?.into_string()
+            .map_err(|v| ArgError::new("Value has to be text:", &v.to_string_lossy()));
+        match name {
+            "--" => only_paths = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--exclude" => options.excluded.push(text()?),
+            "-x" | "--one-file-system" => options.one_file_system = true,
+            "-L" | "--follow-symlinks" => options.follow_symlinks = true,
+            "--threads" => {
+                let threads = text()?;
+                options.threads = threads_of(&threads)
+                    .ok_or_else(|| ArgError::new("Number of threads has to be a positive number:", &threads))?;
+            }
+            "--archives" => options.archives = true,
+            "--stdin" => options.stdin = true,
+            "--null" => options.null = true,
+            "--sizes" => options.sizes = true,
+            "--import" => options.import = Some(PathBuf::from(value()?)),
+            "--export" => options.export = Some(PathBuf::from(value()?)),
+            "--output" => {
+                let format = text()?;
+                options.output = Some(match format.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    _ => return Err(ArgError::new("Output format has to be text, json or csv:", &format)),
+                });
+            }
+            "--sort" => {
+                let order = text()?;
+                options.order = order_of(&order)
+                    .ok_or_else(|| ArgError::new("Sort order has to be disk or count:", &order))?;
+            }
+            _ => return Err(ArgError::new("Unknown option:", &arg.to_string_lossy())),
+        }
+    }
+    if (options.null || options.sizes) && !options.stdin {
+        return Err(ArgError::new("Option only applies together with --stdin:", if options.null { "--null" } else { "--sizes" }));
+    }
+    let sources = [options.stdin, options.import.is_some(), !options.paths.is_empty()];
+    if sources.iter().filter(|s| **s).count() > 1 {
+        return Err(ArgError::new("Only one of paths, --stdin and --import can be given:", &sources_given(&options)));
+    }
+    Ok(Command::Run(options))
+}
+
+// the name has to be text, the value after = is kept as it is; None for names that are not text:
+fn split_value(arg: &OsStr) -> Option<(&str, Option<OsString>)> {
+    let bytes = arg.as_encoded_bytes();
+    match bytes.iter().position(|b| *b == b'=') {
+        Some(i) if bytes.starts_with(b"--") => {
+            let name = std::str::from_utf8(&bytes[..i]).ok()?;
+            // SAFETY: the bytes come from an OsStr and are split right after an ASCII character:
+            let value = unsafe { OsStr::from_encoded_bytes_unchecked(&bytes[i + 1..]) };
+            Some((name, Some(value.to_os_string())))
+        }
+        _ => Some((arg.to_str()?, None)),
+    }
+}
+
+// shared with the config file:
+pub fn threads_of(value: &str) -> Option<usize> {
+    value.parse::<usize>().ok().filter(|n| *n > 0)
+}
+
+pub fn order_of(value: &str) -> Option<Order> {
+    match value {
+        "disk" => Some(Order::DiskUsage),
+        "count" => Some(Order::EntryCount),
+        _ => None,
+    }
+}
+
+fn sources_given(options: &Options) -> String {
+    let mut given: Vec<String> = options.paths.iter().map(|p| p.display().to_string()).collect();
+    if options.stdin {
+        given.push("--stdin".to_string());
+    }
+    if let Some(import) = &options.import {
+        given.push(format!("--import {}", import.display()));
+    }
+    given.join(" ")
+}
+
+fn existing_dir(arg: &OsStr) -> Result<PathBuf, ArgError> {
+    let path = PathBuf::from(arg);
+    if !path.exists() {
+        return Err(ArgError::new("Path does not exist:", &path.display().to_string()));
+    }
+    if !path.is_dir() {
+        return Err(ArgError::new("Path is not a directory:", &path.display().to_string()));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<OsString> {
+        line.split_whitespace().map(OsString::from).collect()
+    }
+
+    fn options(line: &str) -> Options {
+        match parse(&args(line), Options::default()) {
+            Ok(Command::Run(options)) => options,
+            _ => panic!("{} should parse into options", line),
+        }
+    }
+
+    fn error(line: &str) -> ArgError {
+        error_of(&args(line))
+    }
+
+    fn error_of(args: &[OsString]) -> ArgError {
+        match parse(args, Options::default()) {
+            Err(e) => e,
+            Ok(_) => panic!("{:?} should not parse", args),
+        }
+    }
+
+    #[test]
+    fn options_take_values_after_them_or_after_equals() {
+        let dir = std::env::temp_dir();
+        let options = options(&format!("-x --exclude *.tmp --exclude=.git --threads=4 --sort count --output json -- {}", dir.display()));
+        assert!(options.one_file_system);
+        assert!(!options.follow_symlinks);
+        assert_eq!(vec!["*.tmp", ".git"], options.excluded);
+        assert_eq!(4, options.threads);
+        assert_eq!(Order::EntryCount, options.order);
+        assert_eq!(Some(Format::Json), options.output);
+        assert_eq!(vec![dir], options.paths);
+        assert!(options.headless());
+        assert!(matches!(parse(&args("--archives --help --bogus"), Options::default()), Ok(Command::Help)));
+    }
+
+    #[test]
+    fn invalid_arguments_are_told_with_the_argument() {
+        assert_eq!(ArgError::new("Unknown option:", "--bogus"), error("--bogus"));
+        assert_eq!(ArgError::new("Option needs a value:", "--threads"), error("--threads"));
+        assert_eq!(ArgError::new("Number of threads has to be a positive number:", "0"), error("--threads 0"));
+        assert_eq!(ArgError::new("Output format has to be text, json or csv:", "xml"), error("--output=xml"));
+        assert_eq!(ArgError::new("Path does not exist:", "/no/such/dir"), error("/no/such/dir"));
+        assert_eq!(ArgError::new("Option only applies together with --stdin:", "--null"), error("--null"));
+        assert_eq!("Only one of paths, --stdin and --import can be given:", error("--stdin --import scan.export").message);
+        assert!(is_headless(&args("--bogus --export=scan.export")));
+        assert!(!is_headless(&args("--bogus --exported")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_are_kept_byte_for_byte() {
+        use std::os::unix::ffi::OsStringExt;
+        let name = OsString::from_vec(b"scan\xff.export".to_vec());
+        let mut inline = OsString::from("--export=");
+        inline.push(&name);
+        for args in [vec![OsString::from("--export"), name.clone()], vec![inline]] {
+            match parse(&args, Options::default()) {
+                Ok(Command::Run(options)) => assert_eq!(Some(PathBuf::from(&name)), options.export),
+                _ => panic!("{:?} should parse into options", args),
+            }
+        }
+        let missing = OsString::from_vec(b"/no/such/\xff".to_vec());
+        assert_eq!("Path does not exist:", error_of(&[missing]).message);
+        assert_eq!("Value has to be text:", error_of(&[OsString::from("--sort"), name]).message);
+    }
+}
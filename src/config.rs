@@ -0,0 +1,194 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::app_state::Units;
+use crate::cli::{self, Options};
+
+// actions of the list which keys can be changed, as named in ui/keys.slint, with their default keys:
+pub const KEY_ACTIONS: [(&str, &str); 15] = [
+    ("switch_ranking", "i"),
+    ("untouched_filter", "a"),
+    ("breakdown", "e"),
+    ("largest_files", "l"),
+    ("find_duplicates", "d"),
+    ("delete_duplicates", "x"),
+    ("link_duplicates", "h"),
+    ("treemap", "t"),
+    ("sunburst", "s"),
+    ("go_to_path", "g"),
+    ("filter", "/"),
+    ("search", "f"),
+    ("switch_volume", "v"),
+    ("open_folder", "o"),
+    ("switch_theme", "c"),
+];
+
+// in the order of ui/style.slint, which picks them by index:
+const THEMES: [&str; 4] = ["dark", "light", "high-contrast", "colorblind"];
+
+pub struct Config {
+    // defaults for the command line, which overrides them:
+    pub options: Options,
+    pub units: Units,
+    // index of the theme to start with, it can be switched later:
+    pub theme: usize,
+    // only the keys changed from the defaults, by action name:
+    pub keys: Vec<(&'static str, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { options: Options::default(), units: Units::Decimal, theme: 0, keys: Vec::new() }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+// disk_usage/config in $XDG_CONFIG_HOME, falling back to ~/.config and then to %APPDATA% on Windows:
+pub fn config_path() -> Option<PathBuf> {
+    let dir = |name: &str| env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute());
+    dir("XDG_CONFIG_HOME")
+        .or_else(|| dir("HOME").map(|home| home.join(".config")))
+        .or_else(|| dir("APPDATA"))
+        .map(|config| config.join("disk_usage").join("config"))
+}
+
+// a missing file is the same as an empty one:
+pub fn load(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        result => result,
+    }
+}
+
+// one "key = value" setting per line, blank lines and lines starting with # are skipped:
+pub fn parse(text: &str) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    // keys are checked for clashes once all are read, so that two of them can be swapped:
+    let mut key_lines: Vec<(&'static str, usize)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let error = |message: String| ConfigError { line: i + 1, message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(error(format!("Expected key = value, found \"{}\"", line))),
+        };
+        let options = &mut config.options;
+        match key {
+            "exclude" => options.excluded.push(value.to_string()),
+            "one_file_system" => options.one_file_system = bool_of(value).ok_or_else(|| error(expected(key, "true or false", value)))?,
+            "follow_symlinks" => options.follow_symlinks = bool_of(value).ok_or_else(|| error(expected(key, "true or false", value)))?,
+            "archives" => options.archives = bool_of(value).ok_or_else(|| error(expected(key, "true or false", value)))?,
+            "threads" => options.threads = cli::threads_of(value).ok_or_else(|| error(expected(key, "a positive number", value)))?,
+            "sort" => options.order = cli::order_of(value).ok_or_else(|| error(expected(key, "disk or count", value)))?,
+            "units" => config.units = match value {
+                "decimal" => Units::Decimal,
+                "binary" => Units::Binary,
+                _ => return Err(error(expected(key, "decimal or binary", value))),
+            },
+            "theme" => config.theme = THEMES.iter()
+                .position(|t| *t == value)
+                .ok_or_else(|| error(expected(key, &format!("one of {}", THEMES.join(", ")), value)))?,
+            _ => match key.strip_prefix("key.") {
+                Some(action) => {
+                    let action = KEY_ACTIONS.iter()
+                        .map(|(a, _)| *a)
+                        .find(|a| *a == action)
+                        .ok_or_else(|| error(format!("Unknown action \"{}\"", action)))?;
+                    if value.chars().count() != 1 || value.trim().is_empty() {
+                        return Err(error(expected(key, "a single character", value)));
+                    }
+                    config.keys.retain(|(a, _)| *a != action);
+                    config.keys.push((action, value.to_string()));
+                    key_lines.retain(|(a, _)| *a != action);
+                    key_lines.push((action, i + 1));
+                }
+                None => return Err(error(format!("Unknown setting \"{}\"", key))),
+            },
+        }
+    }
+    // the later of two clashing lines is the one told:
+    for ((action, key), (_, line)) in config.keys.iter().zip(&key_lines).rev() {
+        if let Some(other) = clashing_action(&config.keys, action, key) {
+            return Err(ConfigError { line: *line, message: format!("Key \"{}\" is already used by {}", key, other) });
+        }
+    }
+    Ok(config)
+}
+
+fn clashing_action(keys: &[(&'static str, String)], action: &str, key: &str) -> Option<&'static str> {
+    KEY_ACTIONS.iter()
+        .map(|(a, default)| (*a, keys.iter().find(|(changed, _)| changed == a).map(|(_, k)| k.as_str()).unwrap_or(default)))
+        .find(|(a, k)| *a != action && *k == key)
+        .map(|(a, _)| a)
+}
+
+fn bool_of(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn expected(key: &str, allowed: &str, value: &str) -> String {
+    format!("{} has to be {}, found \"{}\"", key, allowed, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use disk_usage::report::Order;
+    use super::*;
+
+    #[test]
+    fn settings_are_read_line_by_line() {
+        let config = parse("\
+# scanning
+exclude = *.tmp
+exclude=node_modules
+threads = 4
+follow_symlinks = true
+
+sort = count
+units = binary
+theme = high-contrast
+key.treemap = s
+key.open_folder = /
+key.sunburst = t
+key.filter = o
+").unwrap();
+        assert_eq!(vec!["*.tmp", "node_modules"], config.options.excluded);
+        assert_eq!(4, config.options.threads);
+        assert!(config.options.follow_symlinks);
+        assert!(!config.options.one_file_system);
+        assert_eq!(Order::EntryCount, config.options.order);
+        assert_eq!(Units::Binary, config.units);
+        assert_eq!(2, config.theme);
+        // keys can be swapped:
+        assert_eq!(vec![("treemap", "s".to_string()), ("open_folder", "/".to_string()),
+                        ("sunburst", "t".to_string()), ("filter", "o".to_string())], config.keys);
+    }
+
+    #[test]
+    fn invalid_lines_are_told_with_their_numbers() {
+        let error = |text: &str| parse(text).err().expect("should not parse");
+        assert_eq!(ConfigError { line: 2, message: "Unknown setting \"colour\"".to_string() }, error("\n colour = red"));
+        assert_eq!(ConfigError { line: 1, message: "Expected key = value, found \"threads\"".to_string() }, error("threads"));
+        assert_eq!("threads has to be a positive number, found \"0\"", error("threads = 0").message);
+        assert_eq!("theme has to be one of dark, light, high-contrast, colorblind, found \"neon\"", error("theme = neon").message);
+        assert_eq!("Unknown action \"fly\"", error("key.fly = q").message);
+        assert_eq!("key.treemap has to be a single character, found \"tt\"", error("key.treemap = tt").message);
+        assert_eq!(ConfigError { line: 3, message: "Key \"m\" is already used by treemap".to_string() },
+                   error("key.treemap = m\n\nkey.search = m"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::ffi::OsStr;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::path_list::path_of;
+use crate::tree::{NodeId, Tree};
+
+const HEADER: &[u8] = b"disk_usage export 1\n";
+
+// a scan saved for looking at later or on another machine: the header line, the root path
+// and then one record for every node, depth first, each ended by NUL so that names can hold anything;
+// a record is "DEPTH KIND SIZE_ON_DISK SIZE TIMESTAMP TIMESTAMP NAME" with the kind being d or f
+// and timestamps in seconds since the epoch, 0 when not known; names are written byte for byte:
+pub fn write_tree<W: Write>(tree: &Tree, mut out: W) -> io::Result<()> {
+    out.write_all(HEADER)?;
+    out.write_all(tree.root_path().as_os_str().as_encoded_bytes())?;
+    out.write_all(b"\0")?;
+    write_children(tree, tree.root(), 1, &mut out)?;
+    out.flush()
+}
+
+fn write_children<W: Write>(tree: &Tree, dir: NodeId, depth: usize, out: &mut W) -> io::Result<()> {
+    for child in tree.children(dir) {
+        let kind = if tree.is_dir(*child) { 'd' } else { 'f' };
+        write!(out, "{} {} {} {} {} {} ", depth, kind, tree.size_on_disk(*child), tree.size_real(*child),
+               seconds_of(tree.modified(*child)), seconds_of(tree.accessed(*child)))?;
+        out.write_all(tree.os_name(*child).as_encoded_bytes())?;
+        out.write_all(b"\0")?;
+        if tree.is_dir(*child) {
+            write_children(tree, *child, depth + 1, out)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_tree<R: BufRead>(mut reader: R) -> io::Result<Tree> {
+    let mut header: Vec<u8> = Vec::new();
+    reader.read_until(b'\n', &mut header)?;
+    if header != HEADER {
+        return Err(invalid("Not a disk_usage export"));
+    }
+    let mut records = reader.split(0);
+    let root_path = records.next().ok_or_else(|| invalid("Export ends before the root path"))??;
+    let mut tree = Tree::new(&path_of(&root_path));
+    // directories being filled, from the root down, with their recorded sizes and the children found so far;
+    // the root has no record, so it sums its children:
+    let mut open: Vec<OpenDir> = vec![OpenDir { id: tree.root(), sizes: None, children: Vec::new() }];
+    for record in records {
+        let record = record?;
+        let fields: Vec<&[u8]> = record.splitn(7, |b| *b == b' ').collect();
+        if fields.len() != 7 {
+            return Err(invalid("Malformed export record"));
+        }
+        let depth = number(fields[0])? as usize;
+        if depth == 0 || depth > open.len() {
+            return Err(invalid("Export record deeper than its directory"));
+        }
+        while open.len() > depth {
+            close(&mut tree, open.pop().expect("Root is never closed"));
+        }
+        let parent = open.last().expect("Root is never closed").id;
+        let name = path_of(fields[6]);
+        let name: &OsStr = name.as_os_str();
+        let (size_on_disk, size_real) = (number(fields[2])?, number(fields[3])?);
+        let (modified, accessed) = (time_of(number(fields[4])?), time_of(number(fields[5])?));
+        let id = match fields[1] {
+            b"d" => {
+                let dir = tree.add_dir(parent, name);
+                tree.set_own_timestamps(dir, modified, accessed);
+                open.push(OpenDir { id: dir, sizes: Some((size_on_disk, size_real)), children: Vec::new() });
+                dir
+            }
+            b"f" => tree.add_file(parent, name, size_on_disk, size_real, modified, accessed),
+            _ => return Err(invalid("Unknown kind of export record")),
+        };
+        // a directory is pushed already, so its own entry goes into its parent:
+        let siblings = open.len() - if tree.is_dir(id) { 2 } else { 1 };
+        open[siblings].children.push(id);
+    }
+    while let Some(dir) = open.pop() {
+        close(&mut tree, dir);
+    }
+    tree.shrink_to_fit();
+    Ok(tree)
+}
+
+struct OpenDir {
+    id: NodeId,
+    sizes: Option<(u64, u64)>,
+    children: Vec<NodeId>,
+}
+
+// recorded sizes are kept over the sums of the children, as archives count as their files:
+fn close(tree: &mut Tree, dir: OpenDir) {
+    tree.set_children(dir.id, dir.children);
+    if let Some((size_on_disk, size_real)) = dir.sizes {
+        tree.set_own_sizes(dir.id, size_on_disk, size_real);
+    }
+}
+
+fn number(field: &[u8]) -> io::Result<u64> {
+    std::str::from_utf8(field).ok()
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| invalid("Malformed number in export record"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn seconds_of(time: Option<SystemTime>) -> u64 {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn time_of(seconds: u64) -> Option<SystemTime> {
+    match seconds {
+        0 => None,
+        s => Some(UNIX_EPOCH + Duration::from_secs(s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::tree::test_tree::*;
+    use super::*;
+
+    #[test]
+    fn exported_trees_are_read_back_the_same() {
+        let mut tree = build(dir("/data", vec![
+            dir("with space", vec![file("a\nb", 10), dir("empty", vec![])]),
+            file("small", 1),
+        ]));
+        let root = tree.root();
+        let touched = tree.add_file(root, OsStr::new("touched"), 4096, 5, Some(UNIX_EPOCH + Duration::from_secs(60)), None);
+        let mut children = tree.children(root).to_vec();
+        children.push(touched);
+        tree.set_children(root, children);
+
+        let mut exported: Vec<u8> = Vec::new();
+        write_tree(&tree, &mut exported).unwrap();
+        let read = read_tree(exported.as_slice()).unwrap();
+
+        assert_eq!(Path::new("/data"), read.root_path());
+        let nodes = |tree: &Tree| tree.descendants(tree.root())
+            .map(|n| (tree.path(n), tree.is_dir(n), tree.size_on_disk(n), tree.size_real(n), tree.modified(n)))
+            .collect::<Vec<_>>();
+        assert_eq!(nodes(&tree), nodes(&read));
+        assert!(read_tree(&b"something else\n"[..]).is_err());
+    }
+
+    #[test]
+    fn directories_keep_their_recorded_sizes_and_timestamps() {
+        let exported = b"disk_usage export 1\n/data\x001 d 4096 300 60 120 release.zip\x002 f 8192 1000 0 0 app\x001 f 10 10 0 0 small\x00";
+        let tree = read_tree(&exported[..]).unwrap();
+        let release = tree.children(tree.root())[0];
+        assert_eq!("release.zip", tree.name(release));
+        assert_eq!((4096, 300), (tree.size_on_disk(release), tree.size_real(release)));
+        assert_eq!((8192, 1000), (tree.size_on_disk(tree.children(release)[0]), tree.size_real(tree.children(release)[0])));
+        assert_eq!((4096 + 10, 310), (tree.size_on_disk(tree.root()), tree.size_real(tree.root())));
+        assert_eq!(Some(UNIX_EPOCH + Duration::from_secs(60)), tree.modified(release));
+        assert_eq!(Some(UNIX_EPOCH + Duration::from_secs(120)), tree.accessed(release));
+    }
+}
@@ -0,0 +1,48 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use disk_usage::{export, report, Tree};
+use crate::cli::Options;
+
+// with --output or --export the scan is printed or saved instead of shown,
+// failures go to stderr and the exit code:
+pub fn run(options: &Options) -> i32 {
+    match load_tree(options).and_then(|tree| save_and_print(&tree, options)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("disk_usage: {}", e);
+            1
+        }
+    }
+}
+
+// the current directory is scanned when nothing else is given:
+fn load_tree(options: &Options) -> io::Result<Tree> {
+    if let Some(list) = options.path_list() {
+        return list.scan(io::stdin().lock());
+    }
+    if let Some(file) = &options.import {
+        return import_tree(file);
+    }
+    let scanner = options.scanner();
+    match options.paths.as_slice() {
+        [] => scanner.scan(&env::current_dir()?),
+        [path] => scanner.scan(path),
+        paths => scanner.scan_all(paths),
+    }
+}
+
+pub fn import_tree(file: &Path) -> io::Result<Tree> {
+    export::read_tree(BufReader::new(File::open(file)?))
+}
+
+fn save_and_print(tree: &Tree, options: &Options) -> io::Result<()> {
+    if let Some(file) = &options.export {
+        export::write_tree(tree, BufWriter::new(File::create(file)?))?;
+    }
+    if let Some(format) = options.output {
+        report::write_report(tree, format, options.order, io::stdout().lock())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+// scanning directories into a compact tree and querying it, without any user interface;
+// the disk_usage binary is a frontend on top of it:
+mod archive;
+pub mod export;
+pub mod path_list;
+pub mod query;
+pub mod report;
+pub mod scanner;
+pub mod source;
+pub mod tree;
+pub mod volume;
+
+pub use path_list::PathList;
+pub use scanner::{Scanner, ScannerBuilder};
+pub use source::{FileSystem, MemorySource, Source};
+pub use tree::{NodeId, Tree};
@@ -1,20 +1,36 @@
 #![windows_subsystem = "windows"]
 
 mod app_state;
+mod cli;
+mod config;
+mod headless;
 mod startup;
 
 use std::thread;
 use std::path::PathBuf;
 use std::sync::Arc;
 use slint::Weak;
-use crate::app_state::AppState;
+use crate::app_state::{AppState, DuplicatesAction};
+use disk_usage::{Scanner, Tree};
+use i_slint_backend_winit::{WinitWindowAccessor, WinitWindowEventResult};
+use i_slint_backend_winit::winit::event::WindowEvent;
+use slint::{ComponentHandle, Model};
 
 fn main() {
-    let target_dir: PathBuf = startup::target_dir();
+    let config = startup::config();
+    let options = startup::options(config.options);
+    if options.headless() {
+        std::process::exit(headless::run(&options));
+    }
+    let scanner = Arc::new(options.scanner());
 
     let app_state = Arc::new(AppState::new());
+    app_state.rank_by(options.order);
+    app_state.show_sizes_in(config.units);
 
     let main_window = MainWindow::new().unwrap();
+    main_window.global::<Style>().set_theme(config.theme as i32);
+    apply_keys(&main_window, &config.keys);
     main_window.on_requested_exit(|| {
         std::process::exit(0);
     });
@@ -22,12 +38,13 @@ fn main() {
         let app_state_clone = Arc::clone(&app_state);
         let main_window_weak = main_window.as_weak();
         main_window.on_step_into(move |i: i32| {
-            match app_state_clone.step_into(i) {
-                Some(items) => {
-                    let very_weak = main_window_weak.unwrap().as_weak();
-                    update_ui_items(very_weak, items);
-                }
-                None => {}
+            if let Some((index, items)) = app_state_clone.step_into(i) {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, index);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
             }
         });
     }
@@ -35,35 +52,398 @@ fn main() {
         let app_state_clone = Arc::clone(&app_state);
         let main_window_weak = main_window.as_weak();
         main_window.on_step_out(move || {
-            match app_state_clone.step_out() {
-                Some((index, items)) => {
+            if let Some((index, items)) = app_state_clone.step_out() {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, index);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_jump_to(move |depth: i32| {
+            if let Some((index, items)) = app_state_clone.step_out_to(depth) {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, index);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_go_back(move || {
+            if let Some((index, items)) = app_state_clone.go_back() {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, index);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_go_forward(move || {
+            if let Some((index, items)) = app_state_clone.go_forward() {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, index);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_open_path_prompt(move || {
+            main_window_weak.unwrap().invoke_ask_path(app_state_clone.current_path_text().into());
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        main_window.on_complete_path(move |text| {
+            app_state_clone.complete_path(&text).into()
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_go_to_path(move |text| {
+            match app_state_clone.go_to_path(&text) {
+                Ok((index, items)) => {
+                    main_window_weak.unwrap().invoke_finish_path_prompt();
                     let very_weak = main_window_weak.unwrap().as_weak();
                     update_ui_items(very_weak, items);
                     let very_weak = main_window_weak.unwrap().as_weak();
                     update_ui_cursor(very_weak, index);
+                    let very_weak = main_window_weak.unwrap().as_weak();
+                    update_ui_location(very_weak, &app_state_clone);
+                }
+                Err(message) => main_window_weak.unwrap().invoke_show_path_error(message.into()),
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_switch_metric(move |cursor: i32| {
+            let (index, items) = app_state_clone.switch_metric(cursor);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_items(very_weak, items);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_cursor(very_weak, index);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_location(very_weak, &app_state_clone);
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_cycle_untouched_filter(move |cursor: i32| {
+            let (index, items) = app_state_clone.cycle_untouched_filter(cursor);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_items(very_weak, items);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_cursor(very_weak, index);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_location(very_weak, &app_state_clone);
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_open_breakdown(move |cursor: i32| {
+            let items = app_state_clone.open_breakdown(cursor);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_items(very_weak, items);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_location(very_weak, &app_state_clone);
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_filter_changed(move |pattern, fuzzy| {
+            let cursor = main_window_weak.unwrap().get_cursor();
+            let (index, items) = app_state_clone.set_name_filter(cursor, &pattern, fuzzy);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_items(very_weak, items);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_cursor(very_weak, index);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_location(very_weak, &app_state_clone);
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_search(move |pattern, fuzzy| {
+            let cursor = main_window_weak.unwrap().get_cursor();
+            if let Some(items) = app_state_clone.search(cursor, &pattern, fuzzy) {
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_items(very_weak, items);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_cursor(very_weak, 0);
+                let very_weak = main_window_weak.unwrap().as_weak();
+                update_ui_location(very_weak, &app_state_clone);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_open_largest_files(move |cursor: i32| {
+            let items = app_state_clone.open_largest_files(cursor);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_items(very_weak, items);
+            let very_weak = main_window_weak.unwrap().as_weak();
+            update_ui_location(very_weak, &app_state_clone);
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_find_duplicates(move |cursor: i32| {
+            main_window_weak.unwrap().set_view_description("searching for duplicates...".into());
+            let app_state_clone = Arc::clone(&app_state_clone);
+            let main_window_weak = main_window_weak.clone();
+            let _hashing_thread = thread::spawn(move || {
+                if let Some(items) = app_state_clone.open_duplicates(cursor) {
+                    let very_weak = main_window_weak.clone();
+                    update_ui_items(very_weak, items);
+                    let very_weak = main_window_weak.clone();
+                    update_ui_cursor(very_weak, 0);
+                }
+                update_ui_location(main_window_weak, &app_state_clone);
+            });
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_delete_duplicates(move |cursor: i32| {
+            if let Some(message) = app_state_clone.prepare_duplicates_action(cursor, DuplicatesAction::Delete) {
+                main_window_weak.unwrap().invoke_ask_confirmation(message.into());
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_link_duplicates(move |cursor: i32| {
+            if let Some(message) = app_state_clone.prepare_duplicates_action(cursor, DuplicatesAction::HardLink) {
+                main_window_weak.unwrap().invoke_ask_confirmation(message.into());
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_request_treemap(move |aspect: f32| {
+            let rects = app_state_clone.treemap(aspect);
+            let value = std::rc::Rc::new(slint::VecModel::from(rects));
+            main_window_weak.unwrap().set_treemap_rects(value.into());
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_request_sunburst(move || {
+            let segments = app_state_clone.sunburst();
+            let value = std::rc::Rc::new(slint::VecModel::from(segments));
+            main_window_weak.unwrap().set_sunburst_segments(value.into());
+        });
+    }
+    {
+        let main_window_weak = main_window.as_weak();
+        main_window.on_sunburst_segment_at(move |x: f32, y: f32| {
+            let segments = main_window_weak.unwrap().get_sunburst_segments();
+            app_state::sunburst_segment_at(segments.iter(), x, y)
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        main_window.on_cancelled(move || {
+            app_state_clone.cancel_pending_action();
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_confirmed(move || {
+            let app_state_clone = Arc::clone(&app_state_clone);
+            let main_window_weak = main_window_weak.clone();
+            let _action_thread = thread::spawn(move || {
+                if let Some((index, items)) = app_state_clone.confirm_pending_action() {
+                    let very_weak = main_window_weak.clone();
+                    update_ui_items(very_weak, items);
+                    let very_weak = main_window_weak.clone();
+                    update_ui_cursor(very_weak, index);
                 }
-                None => {}
+                let very_weak = main_window_weak.clone();
+                update_ui_location(very_weak, &app_state_clone);
+                slint::invoke_from_event_loop(move || {
+                    main_window_weak.unwrap().invoke_finish_confirmation();
+                })
+                    .expect("Invocation of UI update failed");
+            });
+        });
+    }
+
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_list_volumes(move || {
+            let value = std::rc::Rc::new(slint::VecModel::from(app_state_clone.list_volumes()));
+            main_window_weak.unwrap().set_volumes(value.into());
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let scanner = Arc::clone(&scanner);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_pick_volume(move |i: i32| {
+            if let Some(path) = app_state_clone.volume_path(i) {
+                open_folder(main_window_weak.clone(), Arc::clone(&app_state_clone), Arc::clone(&scanner), path);
+            }
+        });
+    }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let scanner = Arc::clone(&scanner);
+        let main_window_weak = main_window.as_weak();
+        main_window.on_choose_folder(move || {
+            // the native dialog runs its own event loop, so the window waits for it:
+            if let Some(path) = rfd::FileDialog::new().set_title("Folder to scan").pick_folder() {
+                open_folder(main_window_weak.clone(), Arc::clone(&app_state_clone), Arc::clone(&scanner), path);
             }
         });
     }
+    {
+        let app_state_clone = Arc::clone(&app_state);
+        let scanner = Arc::clone(&scanner);
+        let main_window_weak = main_window.as_weak();
+        main_window.window().on_winit_window_event(move |_, event| {
+            if let WindowEvent::DroppedFile(path) = event {
+                // a dropped file stands for the folder containing it:
+                let folder = if path.is_dir() { Some(path.clone()) } else { path.parent().map(|p| p.to_path_buf()) };
+                if let Some(folder) = folder {
+                    open_folder(main_window_weak.clone(), Arc::clone(&app_state_clone), Arc::clone(&scanner), folder);
+                }
+            }
+            WinitWindowEventResult::Propagate
+        });
+    }
+
+    if let Some(list) = options.path_list() {
+        load_in_background(main_window.as_weak(), Arc::clone(&app_state), "paths from stdin".to_string(),
+                           move || list.scan(std::io::stdin().lock()));
+    } else if let Some(file) = options.import {
+        load_in_background(main_window.as_weak(), Arc::clone(&app_state), file.display().to_string(),
+                           move || headless::import_tree(&file));
+    } else if !options.paths.is_empty() {
+        scan_in_background(main_window.as_weak(), Arc::clone(&app_state), scanner, options.paths);
+    } else {
+        // without a path given the user picks a volume to start with:
+        main_window.invoke_show_volume_picker();
+    }
+
+    main_window.run().expect("Failed to run the main window");
+}
+
+// keys changed in the config file, the rest keep the defaults from ui/keys.slint:
+fn apply_keys(main_window: &MainWindow, keys: &[(&'static str, String)]) {
+    let global = main_window.global::<Keys>();
+    for (action, key) in keys {
+        let binding = KeyBinding { key: key.into(), label: key.to_uppercase().into() };
+        match *action {
+            "switch_ranking" => global.set_switch_ranking(binding),
+            "untouched_filter" => global.set_untouched_filter(binding),
+            "breakdown" => global.set_breakdown(binding),
+            "largest_files" => global.set_largest_files(binding),
+            "find_duplicates" => global.set_find_duplicates(binding),
+            "delete_duplicates" => global.set_delete_duplicates(binding),
+            "link_duplicates" => global.set_link_duplicates(binding),
+            "treemap" => global.set_treemap(binding),
+            "sunburst" => global.set_sunburst(binding),
+            "go_to_path" => global.set_go_to_path(binding),
+            "filter" => global.set_filter(binding),
+            "search" => global.set_search(binding),
+            "switch_volume" => global.set_switch_volume(binding),
+            "open_folder" => global.set_open_folder(binding),
+            "switch_theme" => global.set_switch_theme(binding),
+            _ => eprintln!("No key binding for action: {}", action),
+        }
+    }
+}
+
+// from the volume picker, the folder chooser or dropping onto the window:
+fn open_folder(weak_window: Weak<MainWindow>, app_state: Arc<AppState>, scanner: Arc<Scanner>, path: PathBuf) {
+    weak_window.unwrap().invoke_finish_volume_picker();
+    scan_in_background(weak_window, app_state, scanner, vec![path]);
+}
 
-    let app_state_clone = Arc::clone(&app_state);
-    let main_window_weak = main_window.as_weak();
+fn scan_in_background(weak_window: Weak<MainWindow>, app_state: Arc<AppState>, scanner: Arc<Scanner>, paths: Vec<PathBuf>) {
+    let names: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+    weak_window.unwrap().set_view_description(format!("scanning {}...", names.join(", ")).into());
     let _scanning_thread = thread::spawn(move || {
-        let items: Vec<SizeItem> = app_state_clone.scan_root_from(target_dir);
-        update_ui_items(main_window_weak, items);
+        let items: Vec<SizeItem> = match app_state.scan_roots_from(paths, &scanner) {
+            Ok(items) => items,
+            Err(e) => {
+                eprintln!("Failed to scan {}, because of: {:?}", names.join(", "), e);
+                Vec::new()
+            }
+        };
+        let very_weak = weak_window.clone();
+        update_ui_items(very_weak, items);
+        let very_weak = weak_window.clone();
+        update_ui_cursor(very_weak, 0);
+        update_ui_location(weak_window, &app_state);
     });
+}
 
-    main_window.run();
+// for trees which are read rather than scanned, from stdin or an exported file:
+fn load_in_background<F>(weak_window: Weak<MainWindow>, app_state: Arc<AppState>, description: String, load: F)
+    where F: FnOnce() -> std::io::Result<Tree> + Send + 'static {
+    weak_window.unwrap().set_view_description(format!("reading {}...", description).into());
+    let _reading_thread = thread::spawn(move || {
+        let items: Vec<SizeItem> = match load() {
+            Ok(tree) => app_state.show_tree(tree),
+            Err(e) => {
+                eprintln!("Failed to read {}, because of: {:?}", description, e);
+                Vec::new()
+            }
+        };
+        let very_weak = weak_window.clone();
+        update_ui_items(very_weak, items);
+        update_ui_location(weak_window, &app_state);
+    });
 }
 
 
 fn update_ui_items(weak_window: Weak<MainWindow>, items: Vec<SizeItem>) {
     slint::invoke_from_event_loop(move || {
         let value = std::rc::Rc::new(slint::VecModel::from(items));
-        let _ = weak_window
-            .unwrap()
-            .set_items(value.into());
+        let wnd = weak_window.unwrap();
+        wnd.set_items(value.into());
+        if wnd.get_treemap_shown() {
+            wnd.invoke_refresh_treemap();
+        }
+        if wnd.get_sunburst_shown() {
+            wnd.invoke_refresh_sunburst();
+        }
     })
         .expect("Invocation of UI update failed");
 }
@@ -71,8 +451,24 @@ fn update_ui_items(weak_window: Weak<MainWindow>, items: Vec<SizeItem>) {
 fn update_ui_cursor(weak_window: Weak<MainWindow>, index: usize) {
     slint::invoke_from_event_loop(move || {
         let wnd = weak_window.unwrap();
-        let _ = wnd.set_cursor(index as i32);
-        let _ = wnd.invoke_center_on_index(index as i32);
+        wnd.set_cursor(index as i32);
+        wnd.invoke_center_on_index(index as i32);
+    })
+        .expect("Invocation of UI update failed");
+}
+
+// view description, breadcrumbs and status bar all follow the current node:
+fn update_ui_location(weak_window: Weak<MainWindow>, app_state: &AppState) {
+    let description = app_state.view_description();
+    let breadcrumbs: Vec<slint::SharedString> = app_state.breadcrumbs().into_iter()
+        .map(|b| b.into())
+        .collect();
+    let status = app_state.status();
+    slint::invoke_from_event_loop(move || {
+        let wnd = weak_window.unwrap();
+        wnd.set_view_description(description.into());
+        wnd.set_breadcrumbs(std::rc::Rc::new(slint::VecModel::from(breadcrumbs)).into());
+        wnd.set_status(status.into());
     })
         .expect("Invocation of UI update failed");
 }
@@ -83,29 +479,179 @@ slint::slint! {
     import { Style } from "./ui/style.slint";
     import { DiskItem } from "./ui/disk-item.slint";
     import { ItemsList } from "./ui/items-list.slint";
+    import { Confirmation } from "./ui/confirmation.slint";
+    import { TreemapRect } from "./ui/treemap-rect-struct.slint";
+    import { Treemap } from "./ui/treemap.slint";
+    import { SunburstSegment } from "./ui/sunburst-segment-struct.slint";
+    import { Sunburst } from "./ui/sunburst.slint";
+    import { Breadcrumbs } from "./ui/breadcrumbs.slint";
+    import { PathPrompt } from "./ui/path-prompt.slint";
+    import { FilterBar } from "./ui/filter-bar.slint";
+    import { VolumePicker } from "./ui/volume-picker.slint";
+    import { KeyBinding, Keys } from "./ui/keys.slint";
     import { ListView } from "std-widgets.slint";
 
-    export { SizeItem }
+    export { SizeItem, SunburstSegment, TreemapRect, KeyBinding, Keys, Style }
 
-    component MainWindow inherits Window {
-        title: "Disk Usage";
+    export component MainWindow inherits Window {
+        title: root.view_description == "" ? "Disk Usage" : "Disk Usage - " + root.view_description;
         background: Style.window-background;
         forward-focus: list;
 
         in property<[SizeItem]> items;
+        in property<string> view_description;
+        in property<[string]> breadcrumbs;
+        in property<string> status;
+        in property<[TreemapRect]> treemap_rects;
+        out property<bool> treemap_shown: false;
+        in property<[SunburstSegment]> sunburst_segments;
+        out property<bool> sunburst_shown: false;
         in-out property <int> cursor <=> list.cursor;
+        in property<[SizeItem]> volumes;
 
         public function center_on_index(index: int) {
             list.center_on_index(index);
         }
 
-        callback requested_exit <=> list.requested_exit;
-        callback step_out <=> list.step_out;
-        callback step_into <=> list.step_into;
+        callback requested_exit;
+        callback step_out;
+        callback step_into(int);
+        callback jump_to(int);
+        callback go_back <=> list.go_back;
+        callback go_forward <=> list.go_forward;
+        callback open_path_prompt <=> list.open_path_prompt;
+        callback go_to_path(string);
+        callback filter_changed(string, bool);
+        callback search(string, bool);
+        callback complete_path(string) -> string;
+        callback request_treemap(float);
+        callback request_sunburst;
+        pure callback sunburst_segment_at(float, float) -> int;
+        callback switch_metric <=> list.switch_metric;
+        callback cycle_untouched_filter <=> list.cycle_untouched_filter;
+        callback open_breakdown <=> list.open_breakdown;
+        callback open_largest_files <=> list.open_largest_files;
+        callback find_duplicates <=> list.find_duplicates;
+        callback delete_duplicates <=> list.delete_duplicates;
+        callback link_duplicates <=> list.link_duplicates;
+        callback confirmed;
+        callback cancelled;
+        callback list_volumes;
+        callback pick_volume(int);
+        callback choose_folder;
+
+        public function show_volume_picker() {
+            root.list_volumes();
+            volume_picker.cursor = 0;
+            volume_picker.visible = true;
+            volume_picker.focus();
+        }
+
+        public function finish_volume_picker() {
+            volume_picker.visible = false;
+            root.focus_shown_view();
+        }
+
+        public function ask_confirmation(message: string) {
+            confirmation.message = message;
+            confirmation.visible = true;
+            confirmation.take_focus();
+        }
+
+        public function ask_path(text: string) {
+            path_prompt.text = text;
+            path_prompt.error = "";
+            path_prompt.visible = true;
+            path_prompt.take_focus();
+        }
+
+        // the same bar filters the list while typing or searches below on Enter:
+        function show_filter_bar(searching: bool) {
+            filter_bar.searching = searching;
+            filter_bar.text = "";
+            filter_bar.visible = true;
+            filter_bar.take_focus();
+        }
+
+        function finish_filter_bar() {
+            filter_bar.visible = false;
+            root.focus_shown_view();
+        }
+
+        public function show_path_error(message: string) {
+            path_prompt.error = message;
+        }
+
+        public function finish_path_prompt() {
+            path_prompt.visible = false;
+            root.focus_shown_view();
+        }
+
+        public function refresh_treemap() {
+            root.request_treemap(treemap.width / treemap.height);
+        }
+
+        public function refresh_sunburst() {
+            root.request_sunburst();
+        }
+
+        public function toggle_treemap() {
+            root.treemap_shown = !root.treemap_shown;
+            root.sunburst_shown = false;
+            if (root.treemap_shown) {
+                root.refresh_treemap();
+            }
+            root.focus_shown_view();
+        }
+
+        public function toggle_sunburst() {
+            root.sunburst_shown = !root.sunburst_shown;
+            root.treemap_shown = false;
+            if (root.sunburst_shown) {
+                root.refresh_sunburst();
+            }
+            root.focus_shown_view();
+        }
+
+        function focus_shown_view() {
+            if (root.treemap_shown) {
+                treemap.focus();
+            } else if (root.sunburst_shown) {
+                sunburst.focus();
+            } else {
+                list.focus();
+                list.center_on_index(list.cursor);
+            }
+        }
 
+        public function finish_confirmation() {
+            confirmation.visible = false;
+            confirmation.busy = false;
+            root.focus_shown_view();
+        }
+
+        Breadcrumbs {
+            x: 0;
+            y: 0;
+            width: parent.width;
+            segments: root.breadcrumbs;
+            jump(depth) => { root.jump_to(depth); }
+        }
         Rectangle {
+            y: 24pt;
+            height: parent.height - 24pt - 20pt;
             list := ItemsList {
+                visible: !root.treemap_shown && !root.sunburst_shown;
                 items: root.items;
+                requested_exit => { root.requested_exit(); }
+                step_out => { root.step_out(); }
+                step_into(i) => { root.step_into(i); }
+                toggle_treemap => { root.toggle_treemap(); }
+                toggle_sunburst => { root.toggle_sunburst(); }
+                open_filter => { root.show_filter_bar(false); }
+                open_search => { root.show_filter_bar(true); }
+                open_volume_picker => { root.show_volume_picker(); }
+                choose_folder => { root.choose_folder(); }
                 y: 6pt;
                 height: parent.height - 12pt;
                 width: parent.width;
@@ -114,6 +660,99 @@ slint::slint! {
                     active: i == list.cursor;
                 }
             }
+            treemap := Treemap {
+                visible: root.treemap_shown;
+                x: 6pt;
+                y: 6pt;
+                width: parent.width - 12pt;
+                height: parent.height - 12pt;
+                rects: root.treemap_rects;
+                items_count: root.items.length;
+                cursor <=> list.cursor;
+                requested_exit => { root.requested_exit(); }
+                step_out => { root.step_out(); }
+                step_into(i) => { root.step_into(i); }
+                toggle_treemap => { root.toggle_treemap(); }
+            }
+            sunburst := Sunburst {
+                visible: root.sunburst_shown;
+                x: 6pt;
+                y: 6pt;
+                width: parent.width - 12pt;
+                height: parent.height - 12pt;
+                segments: root.sunburst_segments;
+                items_count: root.items.length;
+                cursor <=> list.cursor;
+                requested_exit => { root.requested_exit(); }
+                step_out => { root.step_out(); }
+                step_into(i) => { root.step_into(i); }
+                toggle_sunburst => { root.toggle_sunburst(); }
+                segment_at(x, y) => { return root.sunburst_segment_at(x, y); }
+            }
+            path_prompt := PathPrompt {
+                visible: false;
+                x: 20pt;
+                y: (parent.height - self.height) / 2;
+                width: parent.width - 40pt;
+                accepted(text) => { root.go_to_path(text); }
+                cancelled => { root.finish_path_prompt(); }
+                complete(text) => { return root.complete_path(text); }
+            }
+            filter_bar := FilterBar {
+                visible: false;
+                x: 20pt;
+                y: parent.height - self.height - 12pt;
+                width: parent.width - 40pt;
+                changed(text, fuzzy) => { root.filter_changed(text, fuzzy); }
+                accepted(text, fuzzy) => {
+                    if (self.searching) {
+                        root.search(text, fuzzy);
+                    }
+                    root.finish_filter_bar();
+                }
+                cancelled => {
+                    if (!self.searching) {
+                        root.filter_changed("", false);
+                    }
+                    root.finish_filter_bar();
+                }
+            }
+            volume_picker := VolumePicker {
+                visible: false;
+                width: parent.width;
+                height: parent.height;
+                volumes: root.volumes;
+                picked(i) => { root.pick_volume(i); }
+                choose_folder => { root.choose_folder(); }
+                cancelled => {
+                    // at the start there is nothing to return to:
+                    if (root.breadcrumbs.length == 0) {
+                        root.requested_exit();
+                    }
+                    root.finish_volume_picker();
+                }
+            }
+            confirmation := Confirmation {
+                visible: false;
+                x: 20pt;
+                y: (parent.height - self.height) / 2;
+                width: parent.width - 40pt;
+                confirmed => { root.confirmed(); }
+                cancelled => {
+                    root.finish_confirmation();
+                    root.cancelled();
+                }
+            }
+        }
+        Text {
+            x: 8pt;
+            y: parent.height - 20pt;
+            height: 20pt;
+            vertical-alignment: center;
+            color: Style.text-secondary;
+            font-family: Style.font-family;
+            font-size: Style.small-font-size;
+            text: root.status;
         }
     }
 }
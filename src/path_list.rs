@@ -0,0 +1,170 @@
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+
+use crate::scanner::Scanner;
+use crate::source::{FileSystem, Kind, MemorySource, Source};
+use crate::tree::Tree;
+
+// paths listed by other tools, like `find -print0` or `find -printf '%s %p\0'` with sizes in front;
+// sizes on disk are taken equal to sizes, as the list says nothing about clusters:
+pub struct PathList {
+    separator: u8,
+    with_sizes: bool,
+}
+
+impl PathList {
+    pub fn new(separator: u8, with_sizes: bool) -> PathList {
+        PathList { separator, with_sizes }
+    }
+
+    // the root of the tree is the deepest directory containing all paths;
+    // malformed records are skipped and printed to stderr:
+    pub fn scan<R: BufRead>(&self, reader: R) -> io::Result<Tree> {
+        let (root, source) = self.read(reader)?;
+        Scanner::builder().source(source).cluster_size(1).build().scan(&root)
+    }
+
+    fn read<R: BufRead>(&self, reader: R) -> io::Result<(PathBuf, MemorySource)> {
+        let mut source = MemorySource::new(1);
+        let mut root: Option<PathBuf> = None;
+        for record in reader.split(self.separator) {
+            let record = record?;
+            // newline separated lists may come with Windows line endings:
+            let record = record.strip_suffix(b"\r").unwrap_or(&record);
+            if record.is_empty() {
+                continue;
+            }
+            let (size, relative) = match self.parse(record) {
+                Some(parsed) => parsed,
+                None => {
+                    eprintln!("Skipping malformed path list record: {:?}", String::from_utf8_lossy(record));
+                    continue;
+                }
+            };
+            // relative paths, like from `find .`, are relative to the current directory:
+            let path = std::path::absolute(&relative)?;
+            match size {
+                Some(size) => source.add_file(&path, size, None),
+                None => add_from_file_system(&mut source, &path),
+            }
+            root = Some(match root {
+                Some(r) => common_ancestor(&r, &path),
+                None => path,
+            });
+        }
+        let mut root = root.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No paths in the list"))?;
+        if root.as_os_str().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Paths in the list are on different volumes"));
+        }
+        // a single listed file is shown in its directory:
+        if source.metadata(&root)?.kind != Kind::Dir {
+            root.pop();
+        }
+        Ok((root, source))
+    }
+
+    fn parse(&self, record: &[u8]) -> Option<(Option<u64>, PathBuf)> {
+        if !self.with_sizes {
+            return Some((None, path_of(record)));
+        }
+        let space = record.iter().position(|b| *b == b' ')?;
+        let size = std::str::from_utf8(&record[..space]).ok()?.parse().ok()?;
+        let path = &record[space + 1..];
+        if path.is_empty() {
+            return None;
+        }
+        Some((Some(size), path_of(path)))
+    }
+}
+
+// paths without sizes are looked up, the ones gone since listing end up as empty files:
+fn add_from_file_system(source: &mut MemorySource, path: &Path) {
+    match FileSystem.symlink_metadata(path) {
+        Ok(md) if md.kind == Kind::Dir => source.add_dir(path),
+        Ok(md) => source.add_file(path, md.len, md.modified),
+        Err(e) => {
+            eprintln!("Failed to read metadata of the file: {:?} because of {:?}", path, e);
+            source.add_file(path, 0, None);
+        }
+    }
+}
+
+fn common_ancestor(a: &Path, b: &Path) -> PathBuf {
+    a.components()
+        .zip(b.components())
+        .take_while(|(x, y)| x == y)
+        .map(|(x, _)| x)
+        .collect()
+}
+
+#[cfg(unix)]
+pub(crate) fn path_of(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+// paths are UTF-8 in lists produced on Windows:
+#[cfg(not(unix))]
+pub(crate) fn path_of(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tree: &Tree, dir: crate::NodeId) -> Vec<String> {
+        tree.children(dir).iter().map(|&c| tree.name(c).into_owned()).collect()
+    }
+
+    #[test]
+    fn listed_sizes_build_the_tree_below_the_common_directory() {
+        let list = b"4096 /data\x00100 /data/docs/a.txt\x00300 /data/docs/b.txt\x00not a size\x0050 /data/c\x00";
+        let tree = PathList::new(0, true).scan(&list[..]).unwrap();
+        let root = tree.root();
+
+        assert_eq!(std::path::absolute("/data").unwrap(), tree.root_path());
+        assert_eq!(vec!["docs", "c"], names(&tree, root));
+        assert_eq!(vec!["b.txt", "a.txt"], names(&tree, tree.children(root)[0]));
+        assert_eq!(450, tree.size_on_disk(root));
+    }
+
+    #[test]
+    fn malformed_records_are_skipped_and_parents_become_directories() {
+        let list = b"/data/docs 4096\n";
+        assert!(PathList::new(b'\n', true).scan(&list[..]).is_err());
+
+        let list = b"4096 /data/docs\r\n10 /data/docs/a\r\n\n";
+        let tree = PathList::new(b'\n', true).scan(&list[..]).unwrap();
+        assert_eq!(std::path::absolute("/data/docs").unwrap(), tree.root_path());
+        assert_eq!(10, tree.size_real(tree.root()));
+    }
+
+    #[test]
+    fn long_lists_are_read_in_one_go() {
+        let mut list = String::new();
+        for dir in 0..1000 {
+            for file in 0..100 {
+                list.push_str(&format!("1 /data/{}/{}\n", dir, file));
+            }
+        }
+        let tree = PathList::new(b'\n', true).scan(list.as_bytes()).unwrap();
+        assert_eq!(1000, tree.children(tree.root()).len());
+        assert_eq!(100_000, tree.size_real(tree.root()));
+        assert!(tree.children(tree.root()).iter().all(|&dir| tree.children(dir).len() == 100));
+    }
+
+    #[test]
+    fn paths_without_sizes_are_looked_up() {
+        let dir = std::env::temp_dir().join(format!("disk_usage_path_list_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).expect("Failed to create test dir");
+        std::fs::write(dir.join("sub").join("one"), [0_u8; 5]).expect("Failed to write test file");
+        let list = format!("{}\n{}\n", dir.join("sub").join("one").display(), dir.join("gone").display());
+        let tree = PathList::new(b'\n', false).scan(list.as_bytes()).unwrap();
+        std::fs::remove_dir_all(&dir).expect("Failed to remove test dir");
+
+        assert_eq!(dir, tree.root_path());
+        assert_eq!(vec!["sub", "gone"], names(&tree, tree.root()));
+        assert_eq!(5, tree.size_real(tree.root()));
+    }
+}
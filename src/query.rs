@@ -0,0 +1,61 @@
+use crate::tree::{NodeId, Tree};
+
+pub mod breakdown;
+pub mod search;
+
+pub fn files_below(tree: &Tree, node: NodeId) -> Vec<NodeId> {
+    let mut found: Vec<NodeId> = Vec::new();
+    visit_files(tree, node, &mut |file| found.push(file));
+    found
+}
+
+// sorted by size on disk, biggest first:
+pub fn largest_files_below(tree: &Tree, node: NodeId, count: usize) -> Vec<NodeId> {
+    let mut largest: Vec<NodeId> = Vec::with_capacity(count + 1);
+    if count == 0 {
+        return largest;
+    }
+    visit_files(tree, node, &mut |file| {
+        let size = tree.size_on_disk(file);
+        if largest.len() == count && tree.size_on_disk(largest[count - 1]) >= size {
+            return;
+        }
+        let position = largest.partition_point(|f| tree.size_on_disk(*f) >= size);
+        largest.insert(position, file);
+        largest.truncate(count);
+    });
+    largest
+}
+
+fn visit_files<F: FnMut(NodeId)>(tree: &Tree, node: NodeId, visit: &mut F) {
+    for n in tree.children(node) {
+        if tree.is_dir(*n) {
+            visit_files(tree, *n, visit);
+        } else {
+            visit(*n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::test_tree::*;
+
+    #[test]
+    fn largest_files_are_found_at_any_depth() {
+        let tree = build(dir("root", vec![
+            file("small", 10),
+            dir("a", vec![
+                file("medium", 50),
+                dir("b", vec![file("huge", 1000)]),
+            ]),
+            file("big", 100),
+        ]));
+        let largest = largest_files_below(&tree, tree.root(), 2);
+        let paths: Vec<String> = largest.iter().map(|f| tree.relative_path(tree.root(), *f)).collect();
+        let separator = std::path::MAIN_SEPARATOR;
+        assert_eq!(vec![format!("a{}b{}huge", separator, separator), "big".to_string()], paths);
+        assert_eq!(2, tree.parents_below(tree.root(), largest[0]).len());
+    }
+}
@@ -0,0 +1,118 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::tree::{NodeId, Tree};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Grouping {
+    Extension,
+    Category,
+}
+
+impl Grouping {
+    pub fn toggled(&self) -> Grouping {
+        match self {
+            Grouping::Extension => Grouping::Category,
+            Grouping::Category => Grouping::Extension,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Grouping::Extension => "files by extension",
+            Grouping::Category => "files by type",
+        }
+    }
+
+    fn group_name(&self, file_name: &str) -> String {
+        let extension = extension_of(file_name);
+        match self {
+            Grouping::Extension => match extension {
+                Some(ext) => format!(".{}", ext),
+                None => "(no extension)".to_string(),
+            },
+            Grouping::Category => category_of(extension.as_deref()).to_string(),
+        }
+    }
+}
+
+pub struct FileGroup {
+    pub name: String,
+    // sorted by size on disk, biggest first:
+    pub files: Vec<NodeId>,
+}
+
+impl FileGroup {
+    pub fn size_real(&self, tree: &Tree) -> u64 {
+        self.files.iter().map(|f| tree.size_real(*f)).sum()
+    }
+
+    pub fn size_on_disk(&self, tree: &Tree) -> u64 {
+        self.files.iter().map(|f| tree.size_on_disk(*f)).sum()
+    }
+}
+
+pub fn group_files(tree: &Tree, files: Vec<NodeId>, grouping: Grouping) -> Vec<FileGroup> {
+    let mut by_name: HashMap<String, Vec<NodeId>> = HashMap::new();
+    for file in files {
+        by_name.entry(grouping.group_name(&tree.name(file)))
+            .or_default()
+            .push(file);
+    }
+    let mut groups: Vec<FileGroup> = by_name.into_iter()
+        .map(|(name, mut files)| {
+            files.sort_by_key(|n| Reverse(tree.size_on_disk(*n)));
+            FileGroup { name, files }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.size_on_disk(tree).cmp(&a.size_on_disk(tree))
+        .then_with(|| a.name.cmp(&b.name)));
+    groups
+}
+
+// leading dot alone does not make an extension, e.g. ".gitignore":
+fn extension_of(file_name: &str) -> Option<String> {
+    match file_name.rfind('.') {
+        Some(i) if i > 0 && i + 1 < file_name.len() => Some(file_name[i + 1..].to_lowercase()),
+        _ => None,
+    }
+}
+
+fn category_of(extension: Option<&str>) -> &'static str {
+    let extension = match extension {
+        Some(e) => e,
+        None => return "Other",
+    };
+    match extension {
+        "mp4" | "mkv" | "avi" | "mov" | "wmv" | "webm" | "flv" | "m4v" | "mpg" | "mpeg" => "Video",
+        "mp3" | "flac" | "wav" | "ogg" | "aac" | "m4a" | "wma" | "opus" => "Audio",
+        "jpg" | "jpeg" | "png" | "gif" | "bmp" | "tif" | "tiff" | "webp" | "svg" | "ico" | "raw" | "heic" => "Images",
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" | "cab" | "iso" | "jar" => "Archives",
+        "rs" | "c" | "h" | "cpp" | "hpp" | "cc" | "cs" | "java" | "kt" | "go" | "py" | "js" | "ts"
+        | "tsx" | "jsx" | "rb" | "php" | "swift" | "scala" | "sh" | "ps1" | "bat" | "slint" => "Source code",
+        "exe" | "dll" | "so" | "dylib" | "o" | "obj" | "a" | "lib" | "pdb" | "rlib" | "bin" | "msi" | "sys" => "Binaries",
+        "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "txt" | "md" | "rtf" => "Documents",
+        _ => "Other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_is_lowercased_last_suffix() {
+        assert_eq!(Some("gz".to_string()), extension_of("release.TAR.GZ"));
+        assert_eq!(None, extension_of(".gitignore"));
+        assert_eq!(None, extension_of("Makefile"));
+        assert_eq!(None, extension_of("trailing."));
+    }
+
+    #[test]
+    fn unknown_extensions_fall_into_other() {
+        assert_eq!("Video", category_of(Some("mkv")));
+        assert_eq!("Binaries", category_of(Some("dll")));
+        assert_eq!("Other", category_of(Some("xyz")));
+        assert_eq!("Other", category_of(None));
+    }
+}
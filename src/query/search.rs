@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+
+use crate::tree::{NodeId, Tree};
+
+// names are compared ignoring case, either containing the pattern
+// or, when fuzzy, containing its characters in the same order:
+#[derive(Clone)]
+pub struct NameFilter {
+    pattern: String,
+    fuzzy: bool,
+}
+
+impl NameFilter {
+    // empty pattern matches everything, so there is no filter at all:
+    pub fn new(pattern: &str, fuzzy: bool) -> Option<NameFilter> {
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(NameFilter { pattern: pattern.to_lowercase(), fuzzy })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        if !self.fuzzy {
+            return name.contains(&self.pattern);
+        }
+        let mut name_chars = name.chars();
+        self.pattern.chars().all(|p| name_chars.any(|n| n == p))
+    }
+
+    pub fn description(&self) -> String {
+        if self.fuzzy {
+            format!("fuzzy matching \"{}\"", self.pattern)
+        } else {
+            format!("matching \"{}\"", self.pattern)
+        }
+    }
+}
+
+// files and directories anywhere below the node, sorted by size on disk, biggest first:
+pub fn find_matches(tree: &Tree, node: NodeId, filter: &NameFilter) -> Vec<NodeId> {
+    let mut found: Vec<NodeId> = Vec::new();
+    visit_nodes(tree, node, &mut |n| {
+        if filter.matches(&tree.name(n)) {
+            found.push(n);
+        }
+    });
+    found.sort_by_key(|n| Reverse(tree.size_on_disk(*n)));
+    found
+}
+
+fn visit_nodes<F: FnMut(NodeId)>(tree: &Tree, node: NodeId, visit: &mut F) {
+    for n in tree.children(node) {
+        visit(*n);
+        if tree.is_dir(*n) {
+            visit_nodes(tree, *n, visit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use super::*;
+    use crate::tree::test_tree::*;
+
+    #[test]
+    fn fuzzy_matches_characters_in_order() {
+        let filter = NameFilter::new("SRC", true).unwrap();
+        assert!(filter.matches("source"));
+        assert!(!filter.matches("crs"));
+        let filter = NameFilter::new("src", false).unwrap();
+        assert!(filter.matches("my-src-dir"));
+        assert!(!filter.matches("source"));
+        assert!(NameFilter::new("", false).is_none());
+    }
+
+    #[test]
+    fn matches_are_found_below_with_their_parents() {
+        let tree = build(dir("root", vec![
+            dir("logs", vec![file("old.log", 5), file("new.log", 50)]),
+            file("readme", 1),
+        ]));
+        let found = find_matches(&tree, tree.root(), &NameFilter::new("log", false).unwrap());
+        let names: Vec<Cow<str>> = found.iter().map(|f| tree.name(*f)).collect();
+        assert_eq!(vec!["logs", "new.log", "old.log"], names);
+        assert_eq!(vec![find(&tree, "logs")], tree.parents_below(tree.root(), found[1]));
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::{self, Write};
+use std::time::UNIX_EPOCH;
+
+use crate::tree::{NodeId, Tree};
+
+// how a scan is printed for scripts and other tools:
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    // size on disk and path of every directory, like du does:
+    Text,
+    // nested objects for every node:
+    Json,
+    // a row for every node with its full path:
+    Csv,
+}
+
+// children come biggest first, by one of these:
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Order {
+    DiskUsage,
+    EntryCount,
+}
+
+pub fn write_report<W: Write>(tree: &Tree, format: Format, order: Order, mut out: W) -> io::Result<()> {
+    let root = tree.root();
+    match format {
+        Format::Text => write_text(tree, root, order, &mut out)?,
+        Format::Json => {
+            write_json(tree, root, order, &mut out)?;
+            writeln!(out)?;
+        }
+        Format::Csv => {
+            writeln!(out, "path,dir,size,size_on_disk,entries,modified")?;
+            write_csv(tree, root, order, &mut out)?;
+        }
+    }
+    out.flush()
+}
+
+// the tree already sorts by size on disk:
+fn sorted_children(tree: &Tree, node: NodeId, order: Order) -> Vec<NodeId> {
+    let mut children = tree.children(node).to_vec();
+    if order == Order::EntryCount {
+        children.sort_by_cached_key(|c| std::cmp::Reverse(tree.entry_count(*c)));
+    }
+    children
+}
+
+fn write_text<W: Write>(tree: &Tree, node: NodeId, order: Order, out: &mut W) -> io::Result<()> {
+    writeln!(out, "{}\t{}", tree.size_on_disk(node), tree.path(node).display())?;
+    for child in sorted_children(tree, node, order) {
+        if tree.is_dir(child) {
+            write_text(tree, child, order, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(tree: &Tree, node: NodeId, order: Order, out: &mut W) -> io::Result<()> {
+    // the root stands for the whole scanned path:
+    let name = match tree.parent(node) {
+        Some(_) => tree.name(node).into_owned(),
+        None => tree.root_path().display().to_string(),
+    };
+    write!(out, "{{\"name\":{},\"size\":{},\"size_on_disk\":{},\"entries\":{}",
+           json_string(&name), tree.size_real(node), tree.size_on_disk(node), tree.entry_count(node))?;
+    if let Some(seconds) = modified_seconds(tree, node) {
+        write!(out, ",\"modified\":{}", seconds)?;
+    }
+    if tree.is_dir(node) {
+        write!(out, ",\"children\":[")?;
+        for (i, child) in sorted_children(tree, node, order).into_iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write_json(tree, child, order, out)?;
+        }
+        write!(out, "]")?;
+    }
+    write!(out, "}}")
+}
+
+fn write_csv<W: Write>(tree: &Tree, node: NodeId, order: Order, out: &mut W) -> io::Result<()> {
+    let modified = modified_seconds(tree, node).map(|s| s.to_string()).unwrap_or_default();
+    writeln!(out, "{},{},{},{},{},{}", csv_field(&tree.path(node).display().to_string()), tree.is_dir(node),
+             tree.size_real(node), tree.size_on_disk(node), tree.entry_count(node), modified)?;
+    for child in sorted_children(tree, node, order) {
+        write_csv(tree, child, order, out)?;
+    }
+    Ok(())
+}
+
+// of the newest file below directories:
+fn modified_seconds(tree: &Tree, node: NodeId) -> Option<u64> {
+    tree.newest_modified(node)
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// quoted only when needed, with quotes inside doubled:
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree::test_tree::*;
+    use super::*;
+
+    fn report(tree: &Tree, format: Format, order: Order) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        write_report(tree, format, order, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn reports_list_nodes_in_the_chosen_order() {
+        let tree = build(dir("root", vec![
+            file("big \"one\"", 100),
+            dir("many", vec![file("a", 1), file("b", 1)]),
+        ]));
+        let sep = std::path::MAIN_SEPARATOR;
+
+        assert_eq!(format!("102\troot\n2\troot{}many\n", sep), report(&tree, Format::Text, Order::DiskUsage));
+        assert_eq!("{\"name\":\"root\",\"size\":102,\"size_on_disk\":102,\"entries\":4,\"children\":[\
+                    {\"name\":\"many\",\"size\":2,\"size_on_disk\":2,\"entries\":2,\"children\":[\
+                    {\"name\":\"a\",\"size\":1,\"size_on_disk\":1,\"entries\":1},\
+                    {\"name\":\"b\",\"size\":1,\"size_on_disk\":1,\"entries\":1}]},\
+                    {\"name\":\"big \\\"one\\\"\",\"size\":100,\"size_on_disk\":100,\"entries\":1}]}\n",
+                   report(&tree, Format::Json, Order::EntryCount));
+        let csv = report(&tree, Format::Csv, Order::DiskUsage);
+        assert_eq!(Some(format!("\"root{}big \"\"one\"\"\",false,100,100,1,", sep).as_str()), csv.lines().nth(2));
+    }
+}
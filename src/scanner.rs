@@ -0,0 +1,547 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ffi::OsStr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::archive;
+use crate::source::{FileSystem, Kind, Metadata, Source};
+use crate::tree::{NodeId, Tree};
+
+// scans a directory with everything below it into a tree, depth first:
+pub struct Scanner {
+    cluster_size: Option<u64>,
+    follow_symlinks: bool,
+    archives: bool,
+    excluded: Vec<String>,
+    one_file_system: bool,
+    threads: usize,
+    source: Box<dyn Source + Send + Sync>,
+}
+
+#[derive(Default)]
+pub struct ScannerBuilder {
+    cluster_size: Option<u64>,
+    follow_symlinks: bool,
+    archives: bool,
+    excluded: Vec<String>,
+    one_file_system: bool,
+    threads: usize,
+    source: Option<Box<dyn Source + Send + Sync>>,
+}
+
+impl ScannerBuilder {
+    // sizes on disk are rounded up to whole clusters, by default of the volume being scanned:
+    pub fn cluster_size(mut self, cluster_size: u64) -> ScannerBuilder {
+        self.cluster_size = Some(cluster_size);
+        self
+    }
+
+    // by default links are kept as small files, followed they are scanned as what they point to:
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> ScannerBuilder {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    // zip, tar and tar.gz files become directories of their members, sized compressed on disk:
+    pub fn archives(mut self, archives: bool) -> ScannerBuilder {
+        self.archives = archives;
+        self
+    }
+
+    // entries with names matching the pattern, where * stands for any run of characters and ? for one,
+    // are left out together with everything below them; can be given several times:
+    pub fn exclude(mut self, pattern: &str) -> ScannerBuilder {
+        self.excluded.push(pattern.to_string());
+        self
+    }
+
+    // directories of other volumes mounted below the scanned one are kept empty:
+    pub fn one_file_system(mut self, one_file_system: bool) -> ScannerBuilder {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    // entries of the scanned directory are split between that many threads, one when not set:
+    pub fn threads(mut self, threads: usize) -> ScannerBuilder {
+        self.threads = threads;
+        self
+    }
+
+    // the real file system when not set:
+    pub fn source<S: Source + Send + Sync + 'static>(mut self, source: S) -> ScannerBuilder {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn build(self) -> Scanner {
+        Scanner {
+            cluster_size: self.cluster_size,
+            follow_symlinks: self.follow_symlinks,
+            archives: self.archives,
+            excluded: self.excluded,
+            one_file_system: self.one_file_system,
+            threads: self.threads.max(1),
+            source: self.source.unwrap_or_else(|| Box::new(FileSystem)),
+        }
+    }
+}
+
+impl Scanner {
+    pub fn builder() -> ScannerBuilder {
+        ScannerBuilder::default()
+    }
+
+    // unreadable directories and files end up in the tree as empty files, reasons are printed to stderr;
+    // fails only when the cluster size of the volume cannot be told:
+    pub fn scan(&self, path: &Path) -> io::Result<Tree> {
+        let mut tree = Tree::new(path);
+        let root = tree.root();
+        self.scan_into(&mut tree, root, path)?;
+        tree.shrink_to_fit();
+        Ok(tree)
+    }
+
+    // several directories side by side, below a root without a path of its own and named by their full paths;
+    // each one may be on another volume, so each gets its own cluster size,
+    // and ones inside other given directories are left out, so that nothing is counted twice:
+    pub fn scan_all(&self, paths: &[PathBuf]) -> io::Result<Tree> {
+        let mut tree = Tree::new(Path::new(""));
+        let root = tree.root();
+        let mut dirs: Vec<NodeId> = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            let nested = paths.iter().enumerate()
+                .any(|(j, other)| (other != path && path.starts_with(other)) || (j < i && other == path));
+            if nested {
+                continue;
+            }
+            let dir = tree.add_dir(root, path.as_os_str());
+            self.scan_into(&mut tree, dir, path)?;
+            dirs.push(dir);
+        }
+        tree.set_children(root, dirs);
+        tree.shrink_to_fit();
+        Ok(tree)
+    }
+
+    fn scan_into(&self, tree: &mut Tree, dir: NodeId, path: &Path) -> io::Result<()> {
+        let cluster_size = match self.cluster_size {
+            Some(cluster_size) => cluster_size,
+            None => self.source.cluster_size(path)?,
+        }.max(1);
+        let device = if self.one_file_system {
+            match self.source.device(path) {
+                Ok(device) => Some(device),
+                Err(e) => {
+                    eprintln!("Failed to determine file system of: {:?}, because of: {:?}", path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let mut scan = Scan { scanner: self, cluster_size, device, followed: Vec::new() };
+        scan.enter(path);
+        match self.source.read_dir(path) {
+            Ok(entries) if self.threads > 1 => scan_dir_in_parallel(&scan, tree, dir, path, entries),
+            Ok(entries) => scan_dir_recursive_depth_first(&mut scan, tree, dir, entries),
+            Err(e) => eprintln!("Failed to read dir: {:?}, because of: {:?}", path, e),
+        }
+        Ok(())
+    }
+
+    fn is_excluded(&self, name: &OsStr) -> bool {
+        let name: Vec<char> = name.to_string_lossy().chars().collect();
+        self.excluded.iter().any(|pattern| glob_matches(&pattern.chars().collect::<Vec<char>>(), &name))
+    }
+}
+
+struct Scan<'a> {
+    scanner: &'a Scanner,
+    cluster_size: u64,
+    // of the scanned directory, only kept when staying on one file system:
+    device: Option<u64>,
+    // real paths of the directories being scanned, only kept when following links:
+    followed: Vec<PathBuf>,
+}
+
+impl Scan<'_> {
+    fn same_file_system(&self, path: &Path) -> bool {
+        match self.device {
+            Some(device) => self.scanner.source.device(path).map(|d| d == device).unwrap_or(true),
+            None => true,
+        }
+    }
+
+    // false when following a link led back to a directory above:
+    fn enter(&mut self, path: &Path) -> bool {
+        if !self.scanner.follow_symlinks {
+            return true;
+        }
+        let real_path = self.scanner.source.canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.followed.contains(&real_path) {
+            return false;
+        }
+        self.followed.push(real_path);
+        true
+    }
+
+    fn leave(&mut self) {
+        if self.scanner.follow_symlinks {
+            self.followed.pop();
+        }
+    }
+}
+
+// nodes are added to the tree as they are found, directories get their children once all are scanned:
+fn scan_dir_recursive_depth_first(scan: &mut Scan, tree: &mut Tree, dir: NodeId, entries: Vec<std::io::Result<PathBuf>>) {
+    let mut children: Vec<NodeId> = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(p) if p.file_name().is_some_and(|name| scan.scanner.is_excluded(name)) => {}
+            Ok(p) => {
+                children.push(scan_path(scan, tree, dir, &p));
+            }
+            Err(e) => {
+                eprintln!("Failed to process dir entry because of: {:?}", e);
+            }
+        }
+    }
+    tree.set_children(dir, children);
+    scan.leave();
+}
+
+fn scan_path(scan: &mut Scan, tree: &mut Tree, parent: NodeId, path: &Path) -> NodeId {
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let source = &scan.scanner.source;
+    let mut md = match source.symlink_metadata(path) {
+        Ok(md) => md,
+        Err(e) => {
+            eprintln!("Failed to read metadata of the file: {:?} because of {:?}", path, e);
+            return tree.add_file(parent, name, 0, 0, None, None);
+        }
+    };
+    if md.kind == Kind::Symlink && scan.scanner.follow_symlinks {
+        // a broken link stays a link:
+        match source.metadata(path) {
+            Ok(target) => md = target,
+            Err(e) => eprintln!("Failed to follow link: {:?} because of {:?}", path, e),
+        }
+    }
+    match md.kind {
+        // links back up are not read at all, so that they cannot fail to be:
+        Kind::Dir => {
+            if !scan.enter(path) {
+                eprintln!("Not following link: {:?} back to a directory above", path);
+                return tree.add_file(parent, name, 0, 0, None, None);
+            }
+            if !scan.same_file_system(path) {
+                eprintln!("Not crossing into another file system: {:?}", path);
+                scan.leave();
+                return tree.add_dir(parent, name);
+            }
+            match source.read_dir(path) {
+                Ok(entries) => {
+                    let dir = tree.add_dir(parent, name);
+                    scan_dir_recursive_depth_first(scan, tree, dir, entries);
+                    dir
+                }
+                Err(e) => {
+                    eprintln!("Failed to read dir: {:?}, because of: {:?}", path, e);
+                    scan.leave();
+                    tree.add_file(parent, name, 0, 0, None, None)
+                }
+            }
+        }
+        Kind::File | Kind::Symlink => {
+            let size_on_disk = md.len.div_ceil(scan.cluster_size) * scan.cluster_size;
+            if let Some(members) = scan_archive(scan, path, &md) {
+                return archive::add_archive(tree, parent, name, members, size_on_disk, md.len);
+            }
+            tree.add_file(parent, name, size_on_disk, md.len, md.modified, md.accessed)
+        }
+        Kind::Other => tree.add_file(parent, name, 0, 0, None, None),
+    }
+}
+
+// each thread scans whole entries into a tree of its own, which are then grafted in the original order:
+fn scan_dir_in_parallel(scan: &Scan, tree: &mut Tree, dir: NodeId, path: &Path, entries: Vec<std::io::Result<PathBuf>>) {
+    let paths: Vec<PathBuf> = entries.into_iter()
+        .filter_map(|entry| match entry {
+            Ok(p) if p.file_name().is_some_and(|name| scan.scanner.is_excluded(name)) => None,
+            Ok(p) => Some(p),
+            Err(e) => {
+                eprintln!("Failed to process dir entry because of: {:?}", e);
+                None
+            }
+        })
+        .collect();
+    let next = AtomicUsize::new(0);
+    let scanned: Mutex<Vec<(usize, Arc<Tree>, NodeId)>> = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..scan.scanner.threads.min(paths.len()) {
+            scope.spawn(|| {
+                let mut part = Tree::new(path);
+                let root = part.root();
+                let mut part_scan = Scan { followed: scan.followed.clone(), ..*scan };
+                let mut nodes: Vec<(usize, NodeId)> = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= paths.len() {
+                        break;
+                    }
+                    nodes.push((i, scan_path(&mut part_scan, &mut part, root, &paths[i])));
+                }
+                let part = Arc::new(part);
+                scanned.lock()
+                    .expect("Failed to acquire mutex lock on scanned parts")
+                    .extend(nodes.into_iter().map(|(i, node)| (i, Arc::clone(&part), node)));
+            });
+        }
+    });
+    let mut scanned = scanned.into_inner()
+        .expect("Failed to take scanned parts out of mutex");
+    scanned.sort_by_key(|(i, _, _)| *i);
+    let children = scanned.iter()
+        .map(|(_, part, node)| tree.graft(dir, part, *node))
+        .collect();
+    tree.set_children(dir, children);
+}
+
+// * stands for any run of characters and ? for exactly one, everything else matches itself:
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| glob_matches(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && glob_matches(rest, &name[1..]),
+    }
+}
+
+// unreadable archives are kept as plain files:
+fn scan_archive(scan: &Scan, path: &Path, md: &Metadata) -> Option<archive::Members> {
+    if !scan.scanner.archives || md.kind != Kind::File {
+        return None;
+    }
+    let format = archive::format_of(path.file_name()?)?;
+    match scan.scanner.source.open(path).and_then(|r| archive::read_members(r, format, md.len)) {
+        Ok(members) => Some(members),
+        Err(e) => {
+            eprintln!("Failed to read archive: {:?} because of {:?}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+    use crate::source::MemorySource;
+    use super::*;
+
+    fn memory_source() -> MemorySource {
+        let mut source = MemorySource::new(1000);
+        source.add_file("/data/small", 1, Some(SystemTime::UNIX_EPOCH + Duration::from_secs(60)));
+        source.add_file("/data/docs/big", 2500, None);
+        source.add_unreadable_dir("/data/secret");
+        source.add_symlink("/data/link", "docs");
+        source.add_symlink("/data/docs/loop", "/data");
+        source.add_symlink("/data/broken", "missing");
+        source
+    }
+
+    fn child(tree: &Tree, dir: NodeId, name: &str) -> NodeId {
+        *tree.children(dir).iter().find(|&&c| tree.name(c) == name).expect("No such child")
+    }
+
+    #[test]
+    fn sizes_on_disk_are_rounded_up_to_clusters() {
+        let dir = std::env::temp_dir().join(format!("disk_usage_scanner_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).expect("Failed to create test dir");
+        fs::write(dir.join("one"), [0_u8; 1]).expect("Failed to write test file");
+        fs::write(dir.join("sub").join("two"), [0_u8; 5000]).expect("Failed to write test file");
+        let tree = Scanner::builder().cluster_size(4096).build().scan(&dir).unwrap();
+        fs::remove_dir_all(&dir).expect("Failed to remove test dir");
+
+        assert_eq!(5001, tree.size_real(tree.root()));
+        assert_eq!(3 * 4096, tree.size_on_disk(tree.root()));
+        assert_eq!("sub", tree.name(tree.children(tree.root())[0]));
+        assert_eq!(dir.join("sub").join("two"), tree.path(tree.children(tree.children(tree.root())[0])[0]));
+    }
+
+    #[test]
+    fn memory_source_is_scanned_with_its_cluster_size() {
+        let tree = Scanner::builder().source(memory_source()).build().scan(Path::new("/data")).unwrap();
+        let root = tree.root();
+
+        assert_eq!(vec!["docs", "broken", "link", "small", "secret"],
+                   tree.children(root).iter().map(|&c| tree.name(c)).collect::<Vec<_>>());
+        assert_eq!(3000 + 1000, tree.size_on_disk(child(&tree, root, "docs")));
+        assert_eq!(1000, tree.size_on_disk(child(&tree, root, "small")));
+        assert_eq!(60, tree.newest_modified(child(&tree, root, "small")).unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs());
+    }
+
+    #[test]
+    fn unreadable_directories_are_kept_as_empty_files() {
+        let tree = Scanner::builder().source(memory_source()).build().scan(Path::new("/data")).unwrap();
+        let secret = child(&tree, tree.root(), "secret");
+
+        assert!(!tree.is_dir(secret));
+        assert_eq!(0, tree.size_real(secret));
+
+        let tree = Scanner::builder().source(memory_source()).build().scan(Path::new("/missing")).unwrap();
+        assert!(tree.children(tree.root()).is_empty());
+    }
+
+    #[test]
+    fn symlinks_are_kept_as_links_unless_followed() {
+        let tree = Scanner::builder().source(memory_source()).build().scan(Path::new("/data")).unwrap();
+        let link = child(&tree, tree.root(), "link");
+        assert!(!tree.is_dir(link));
+        assert_eq!("docs".len() as u64, tree.size_real(link));
+        assert_eq!(1 + 2500 + "/data".len() as u64 + 4 + 7, tree.size_real(tree.root()));
+
+        let tree = Scanner::builder().source(memory_source()).follow_symlinks(true).build().scan(Path::new("/data")).unwrap();
+        let link = child(&tree, tree.root(), "link");
+        assert!(tree.is_dir(link));
+        assert_eq!(2500, tree.size_real(link));
+        // the loop back to the root is cut off in both the directory and the link to it:
+        assert!(!tree.is_dir(child(&tree, link, "loop")));
+        assert!(!tree.is_dir(child(&tree, child(&tree, tree.root(), "docs"), "loop")));
+        assert_eq!("missing".len() as u64, tree.size_real(child(&tree, tree.root(), "broken")));
+    }
+
+    fn zip_with(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            std::io::Write::write_all(&mut zip, contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn tar_gz_with(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mtime(60);
+            tar.append_data(&mut header, name, *contents).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn archives_source(cluster_size: u64) -> MemorySource {
+        let mut source = MemorySource::new(cluster_size);
+        source.add_file_with_contents("/data/release.zip", zip_with(&[("bin/app", &[0_u8; 10000]), ("README", b"read me")]), None);
+        source.add_file_with_contents("/data/src.tar.gz", tar_gz_with(&[("src/main.rs", &[b'x'; 3000]), ("src/lib.rs", &[b'y'; 1000])]), None);
+        source.add_file_with_contents("/data/broken.zip", b"not a zip".to_vec(), None);
+        source.add_file("/data/other", 5000, None);
+        source
+    }
+
+    #[test]
+    fn archives_are_scanned_as_directories_when_asked() {
+        let tar_gz_len = tar_gz_with(&[("src/main.rs", &[b'x'; 3000]), ("src/lib.rs", &[b'y'; 1000])]).len() as u64;
+        let zip_len = zip_with(&[("bin/app", &[0_u8; 10000]), ("README", b"read me")]).len() as u64;
+
+        let tree = Scanner::builder().source(archives_source(1)).archives(true).build().scan(Path::new("/data")).unwrap();
+        let root = tree.root();
+        let release = child(&tree, root, "release.zip");
+        let app = child(&tree, child(&tree, release, "bin"), "app");
+        assert_eq!(10000, tree.size_real(app));
+        assert!(tree.size_on_disk(app) < 1000);
+        // the archive itself is still as big as its file:
+        assert_eq!(zip_len, tree.size_real(release));
+        assert_eq!(PathBuf::from("/data/release.zip/bin/app"), tree.path(app));
+
+        let src = child(&tree, child(&tree, root, "src.tar.gz"), "src");
+        assert_eq!(4000, tree.size_real(src));
+        assert!(tree.size_on_disk(src) <= tar_gz_len);
+        assert!(tree.size_on_disk(child(&tree, src, "main.rs")) > tree.size_on_disk(child(&tree, src, "lib.rs")));
+        assert_eq!(Some(SystemTime::UNIX_EPOCH + Duration::from_secs(60)), tree.newest_modified(src));
+        assert!(tree.is_in_archive(src));
+        assert!(!tree.is_in_archive(release));
+
+        let broken = child(&tree, root, "broken.zip");
+        assert!(!tree.is_dir(broken));
+        assert_eq!(9, tree.size_real(broken));
+    }
+
+    #[test]
+    fn archives_count_as_their_files_in_directories_above() {
+        let scan = |archives: bool| Scanner::builder().source(archives_source(4096))
+            .archives(archives).build()
+            .scan(Path::new("/data")).unwrap();
+        let plain = scan(false);
+        let tree = scan(true);
+        assert_eq!(plain.size_on_disk(plain.root()), tree.size_on_disk(tree.root()));
+        assert_eq!(plain.size_real(plain.root()), tree.size_real(tree.root()));
+        let release = child(&tree, tree.root(), "release.zip");
+        assert!(tree.is_dir(release));
+        assert_eq!(4096, tree.size_on_disk(release));
+    }
+
+    #[test]
+    fn several_roots_are_scanned_side_by_side() {
+        let source = memory_source();
+        let tree = Scanner::builder().source(source).build()
+            .scan_all(&[PathBuf::from("/data/docs"), PathBuf::from("/data/secret"), PathBuf::from("/data/docs"), PathBuf::from("/data")]).unwrap();
+        let root = tree.root();
+
+        // the other two are inside /data:
+        assert_eq!(vec!["/data"], tree.children(root).iter().map(|&c| tree.name(c)).collect::<Vec<_>>());
+        assert_eq!(tree.size_real(tree.children(root)[0]), tree.size_real(root));
+
+        let mut source = MemorySource::new(1000);
+        source.add_file("/var/log", 10, None);
+        source.add_file("/home/me", 1, None);
+        let tree = Scanner::builder().source(source).build()
+            .scan_all(&[PathBuf::from("/home"), PathBuf::from("/var")]).unwrap();
+        let var = child(&tree, tree.root(), "/var");
+        assert_eq!(2000, tree.size_on_disk(tree.root()));
+        assert_eq!(PathBuf::from("/var/log"), tree.path(tree.children(var)[0]));
+        assert_eq!(PathBuf::from("/var"), tree.path(var));
+    }
+
+    #[test]
+    fn excluded_names_are_left_out() {
+        let tree = Scanner::builder().source(memory_source()).exclude("*.tmp").exclude("do?s").build()
+            .scan(Path::new("/data")).unwrap();
+        assert_eq!(vec!["broken", "link", "small", "secret"],
+                   tree.children(tree.root()).iter().map(|&c| tree.name(c)).collect::<Vec<_>>());
+        assert!(glob_matches(&['*', 'x'], &['x']));
+        assert!(!glob_matches(&['?'], &[]));
+    }
+
+    #[test]
+    fn other_file_systems_are_kept_empty_when_asked() {
+        let mut source = memory_source();
+        source.add_mount("/data/docs/usb", 1);
+        source.add_file("/data/docs/usb/photo", 5000, None);
+        let tree = Scanner::builder().source(source).one_file_system(true).build().scan(Path::new("/data")).unwrap();
+        let usb = child(&tree, child(&tree, tree.root(), "docs"), "usb");
+        assert!(tree.is_dir(usb));
+        assert!(tree.children(usb).is_empty());
+    }
+
+    #[test]
+    fn threads_scan_the_same_tree() {
+        let single = Scanner::builder().source(memory_source()).follow_symlinks(true).build().scan(Path::new("/data")).unwrap();
+        let parallel = Scanner::builder().source(memory_source()).follow_symlinks(true).threads(3).build().scan(Path::new("/data")).unwrap();
+        assert_eq!(single.node_count(), parallel.node_count());
+        assert_eq!(single.size_on_disk(single.root()), parallel.size_on_disk(parallel.root()));
+        let paths = |tree: &Tree| tree.descendants(tree.root()).map(|n| tree.path(n)).collect::<Vec<_>>();
+        assert_eq!(paths(&single), paths(&parallel));
+
+        let parallel = Scanner::builder().source(archives_source(1)).archives(true).threads(2).build().scan(Path::new("/data")).unwrap();
+        let release = child(&parallel, parallel.root(), "release.zip");
+        assert!(parallel.is_in_archive(child(&parallel, release, "README")));
+        assert!(!parallel.is_in_archive(child(&parallel, parallel.root(), "other")));
+        assert_eq!(archives_source(1).metadata(Path::new("/data/release.zip")).unwrap().len, parallel.size_real(release));
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::volume;
+
+mod memory;
+
+pub use memory::MemorySource;
+
+// where the scanner gets directory listings and metadata from, the real file system or anything else:
+pub trait Source {
+    // of the entry itself, links are not followed:
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata>;
+    // of what the path points to, following links:
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    // paths of the entries, failing to read one of them does not fail the whole directory:
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>>;
+    // path with all links resolved, which tells whether a followed link leads back up:
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+    fn cluster_size(&self, path: &Path) -> io::Result<u64>;
+    // tells apart volumes and other file systems mounted below the scanned directory:
+    fn device(&self, path: &Path) -> io::Result<u64>;
+    // contents of a file, for looking inside archives:
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>>;
+}
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+    // devices, sockets and the like:
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub kind: Kind,
+    pub len: u64,
+    // timestamps are not available on every platform and file system:
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+}
+
+pub struct FileSystem;
+
+impl Source for FileSystem {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::symlink_metadata(path).map(|md| metadata_of(&md))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path).map(|md| metadata_of(&md))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        Ok(fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn cluster_size(&self, path: &Path) -> io::Result<u64> {
+        volume::cluster_size_for_path(path)
+    }
+
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        volume::device_id(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+fn metadata_of(md: &fs::Metadata) -> Metadata {
+    let kind = if md.is_symlink() {
+        Kind::Symlink
+    } else if md.is_dir() {
+        Kind::Dir
+    } else if md.is_file() {
+        Kind::File
+    } else {
+        Kind::Other
+    };
+    Metadata { kind, len: md.len(), modified: md.modified().ok(), accessed: md.accessed().ok() }
+}
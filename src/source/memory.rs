@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::io::{self, Cursor};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{Kind, Metadata, ReadSeek, Source};
+
+// the same limit as Linux has:
+const MAX_LINK_HOPS: usize = 40;
+
+enum MemoryEntry {
+    // contents are empty unless given, whatever the length:
+    File { len: u64, modified: Option<SystemTime>, contents: Vec<u8> },
+    Dir { readable: bool },
+    // relative targets start from the directory containing the link:
+    Symlink(PathBuf),
+}
+
+// file system kept in memory, for scanning without touching the disk;
+// parent directories of added entries are created along the way:
+pub struct MemorySource {
+    entries: BTreeMap<PathBuf, MemoryEntry>,
+    // directories other file systems are mounted on, everything else is on device 0:
+    mounts: BTreeMap<PathBuf, u64>,
+    cluster_size: u64,
+}
+
+impl MemorySource {
+    pub fn new(cluster_size: u64) -> MemorySource {
+        MemorySource { entries: BTreeMap::new(), mounts: BTreeMap::new(), cluster_size }
+    }
+
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P, len: u64, modified: Option<SystemTime>) {
+        self.add(path.as_ref(), MemoryEntry::File { len, modified, contents: Vec::new() });
+    }
+
+    pub fn add_file_with_contents<P: AsRef<Path>>(&mut self, path: P, contents: Vec<u8>, modified: Option<SystemTime>) {
+        let len = contents.len() as u64;
+        self.add(path.as_ref(), MemoryEntry::File { len, modified, contents });
+    }
+
+    pub fn add_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.add(path.as_ref(), MemoryEntry::Dir { readable: true });
+    }
+
+    // listing it fails with permission denied:
+    pub fn add_unreadable_dir<P: AsRef<Path>>(&mut self, path: P) {
+        self.add(path.as_ref(), MemoryEntry::Dir { readable: false });
+    }
+
+    pub fn add_symlink<P: AsRef<Path>, T: AsRef<Path>>(&mut self, path: P, target: T) {
+        self.add(path.as_ref(), MemoryEntry::Symlink(target.as_ref().to_path_buf()));
+    }
+
+    // the directory and everything below it, up to other mounts, end up on the given device:
+    pub fn add_mount<P: AsRef<Path>>(&mut self, path: P, device: u64) {
+        self.add_dir(path.as_ref());
+        self.mounts.insert(path.as_ref().to_path_buf(), device);
+    }
+
+    // anything with entries below it is a directory, whether it was added as a file before or after:
+    fn add(&mut self, path: &Path, entry: MemoryEntry) {
+        for ancestor in path.ancestors().skip(1) {
+            if ancestor.as_os_str().is_empty() {
+                break;
+            }
+            let parent = self.entries.entry(ancestor.to_path_buf()).or_insert(MemoryEntry::Dir { readable: true });
+            if let MemoryEntry::File { .. } = parent {
+                *parent = MemoryEntry::Dir { readable: true };
+            }
+        }
+        if let (Some(MemoryEntry::Dir { .. }), MemoryEntry::File { .. }) = (self.entries.get(path), &entry) {
+            return;
+        }
+        self.entries.insert(path.to_path_buf(), entry);
+    }
+
+    // replaces links on the way with their targets, the last one only when following:
+    fn resolve(&self, path: &Path, follow_last: bool, hops: usize) -> io::Result<PathBuf> {
+        if hops > MAX_LINK_HOPS {
+            return Err(io::Error::other("too many levels of symbolic links"));
+        }
+        let components: Vec<_> = path.components().collect();
+        let mut resolved = PathBuf::new();
+        for (i, component) in components.iter().enumerate() {
+            resolved.push(component);
+            let is_last = i + 1 == components.len();
+            if let Some(MemoryEntry::Symlink(target)) = self.entries.get(&resolved) {
+                if !is_last || follow_last {
+                    let target = match resolved.parent() {
+                        Some(parent) => parent.join(target),
+                        None => target.clone(),
+                    };
+                    resolved = self.resolve(&target, true, hops + 1)?;
+                }
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn entry(&self, path: &Path) -> io::Result<&MemoryEntry> {
+        self.entries.get(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{:?} not found", path)))
+    }
+
+    fn metadata_of(&self, path: &Path) -> io::Result<Metadata> {
+        let (kind, len, modified) = match self.entry(path)? {
+            MemoryEntry::File { len, modified, .. } => (Kind::File, *len, *modified),
+            MemoryEntry::Dir { .. } => (Kind::Dir, 0, None),
+            MemoryEntry::Symlink(target) => (Kind::Symlink, target.as_os_str().len() as u64, None),
+        };
+        Ok(Metadata { kind, len, modified, accessed: None })
+    }
+}
+
+impl Source for MemorySource {
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.metadata_of(&self.resolve(path, false, 0)?)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.metadata_of(&self.resolve(path, true, 0)?)
+    }
+
+    // sorted by name, so that scans come out the same every time:
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        let resolved = self.resolve(path, true, 0)?;
+        match self.entry(&resolved)? {
+            MemoryEntry::Dir { readable: true } => {}
+            MemoryEntry::Dir { readable: false } => return Err(io::Error::new(io::ErrorKind::PermissionDenied, "permission denied")),
+            _ => return Err(io::Error::other(format!("{:?} is not a directory", path))),
+        }
+        // paths compare by components, so everything below the directory comes right after it:
+        Ok(self.entries.range::<Path, _>((Bound::Excluded(resolved.as_path()), Bound::Unbounded))
+            .map(|(p, _)| p)
+            .take_while(|p| p.starts_with(&resolved))
+            .filter(|p| p.parent() == Some(resolved.as_path()))
+            .filter_map(|p| p.file_name())
+            .map(|name| Ok(path.join(name)))
+            .collect())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path, true, 0)?;
+        self.entry(&resolved)?;
+        Ok(resolved)
+    }
+
+    fn cluster_size(&self, _path: &Path) -> io::Result<u64> {
+        Ok(self.cluster_size)
+    }
+
+    fn device(&self, path: &Path) -> io::Result<u64> {
+        let resolved = self.resolve(path, true, 0)?;
+        Ok(resolved.ancestors()
+            .find_map(|a| self.mounts.get(a))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+        match self.entry(&self.resolve(path, true, 0)?)? {
+            MemoryEntry::File { contents, .. } => Ok(Box::new(Cursor::new(contents.clone()))),
+            _ => Err(io::Error::other(format!("{:?} is not a file", path))),
+        }
+    }
+}
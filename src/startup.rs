@@ -1,50 +1,80 @@
 use std::env;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use crate::cli::{self, Command, Options};
+use crate::config::{self, Config};
+#[cfg(windows)]
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
 
-pub fn target_dir() -> PathBuf {
-    path_or_error_dialog(
-        determine_root_directory())
+// read before the arguments, which override it; without a config file everything stays at the defaults:
+pub fn config() -> Config {
+    let path = match config::config_path() {
+        Some(path) => path,
+        None => return Config::default(),
+    };
+    let parsed = config::load(&path)
+        .map_err(|e| format!("Failed to read the config file, because of: {}", e))
+        .and_then(|text| config::parse(&text)
+            .map_err(|e| format!("{} in line {} of the config file:", e.message, e.line)));
+    match parsed {
+        Ok(config) => config,
+        Err(message) => report_error(message, path.display().to_string()),
+    }
 }
 
-fn determine_root_directory() -> Result<PathBuf, ErrMessage> {
-    let arg = env::args().nth(1);
-    match arg {
-        Some(a) => {
-            let path = PathBuf::from(&a);
-            if !path.exists() {
-                return Err(ErrMessage {
-                    message: "Path does not exist:".into(),
-                    path: a.into()
-                });
-            }
-            if !path.is_dir() {
-                return Err(ErrMessage {
-                    message: "Path is not a directory:".into(),
-                    path: a.into()
-                });
+// help and version are printed and the program exits, as it does on invalid arguments:
+pub fn options(defaults: Options) -> Options {
+    let args = args();
+    let command = cli::parse(&args, defaults);
+    if cli::is_headless(&args) || !matches!(command, Ok(Command::Run(_))) {
+        attach_console();
+    }
+    match command {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            std::process::exit(0);
+        }
+        Ok(Command::Version) => {
+            println!("disk_usage {}", env!("CARGO_PKG_VERSION"));
+            std::process::exit(0);
+        }
+        Err(e) => {
+            if cli::is_headless(&args) {
+                eprint!("{}", cli::USAGE);
             }
-            return Ok(path);
+            report_error(e.message, e.argument)
         }
-        None => {}
     }
-    env::current_dir()
-        .map_err(|_e| ErrMessage {
-            message: "Cannot determine current directory".into(),
-            path: "".into()
-        })
 }
 
-fn path_or_error_dialog(desired_target: Result<PathBuf, ErrMessage>) -> PathBuf {
-    match desired_target {
-        Ok(path) => return path,
-        Err(e) => {
-            let dialog = ErrorDialog::new().unwrap();
-            dialog.invoke_set_message(e.into());
-            dialog.on_close_confirmed(|| {
-                std::process::exit(1);
-            });
-            dialog.run();
-        }
+// paths are taken as they are, even when they are not valid Unicode:
+fn args() -> Vec<OsString> {
+    env::args_os().skip(1).collect()
+}
+
+// without a window the program has no console of its own on Windows, so printing goes to the one it was started from:
+#[cfg(windows)]
+fn attach_console() {
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+// told in a dialog, or on stderr when running without a window:
+fn report_error(message: String, argument: String) -> ! {
+    if cli::is_headless(&args()) {
+        eprintln!("disk_usage: {} {}", message, argument);
+        std::process::exit(2);
+    }
+    let dialog = ErrorDialog::new().unwrap();
+    dialog.invoke_set_message(ErrMessage { message: message.as_str().into(), path: argument.as_str().into() });
+    dialog.on_close_confirmed(|| {
+        std::process::exit(1);
+    });
+    // without a window to show it in, the error still gets out:
+    if dialog.run().is_err() {
+        eprintln!("disk_usage: {} {}", message, argument);
     }
     std::process::exit(1);
 }
@@ -61,9 +91,9 @@ slint::slint! {
 
     export { ErrMessage }
 
-    component ErrorDialog inherits Dialog {
+    export component ErrorDialog inherits Dialog {
 
-        background: #1f1f1f;
+        background: Style.window-background;
 
         public function set_message(s: ErrMessage) {
             message.text = s.message;
@@ -74,21 +104,21 @@ slint::slint! {
         forward-focus: fs;
 
         Rectangle {
-            height: 60pt;
-            width: path.width + 20pt;
+            height: path.y + path.preferred-height + 12pt;
+            width: max(message.preferred-width, path.preferred-width) + 20pt;
             message := Text {
                 x: 6pt;
                 y: 6pt;
                 color: Style.text-main;
-                font-family: "Segoe UI";
-                font-size: 12pt;
+                font-family: Style.font-family;
+                font-size: Style.font-size;
             }
             path := Text {
                 x: 6pt;
-                y: 32pt;
+                y: message.y + message.preferred-height + 8pt;
                 color: Style.text-main;
-                font-family: "Consolas";
-                font-size: 12pt;
+                font-family: Style.font-family;
+                font-size: Style.font-size;
             }
             fs := FocusScope {
                 key-pressed(event) => {
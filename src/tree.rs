@@ -0,0 +1,733 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// all nodes of a scan live in flat vectors: fixed size entries, names packed into a single buffer
+// and children of every directory stored next to each other, so that huge volumes fit into memory;
+// ids are indexes of the entries, they never change for the whole life of the tree:
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NodeId(u32);
+
+// files have no directory record at all, which tells them apart from empty directories:
+const NO_DIR: u32 = u32::MAX;
+const NO_PARENT: u32 = u32::MAX;
+
+#[derive(Clone)]
+struct Entry {
+    // names are packed in the order of ids, so each one ends where the next one starts:
+    name_start: u32,
+    parent: u32,
+    dir: u32,
+    // timestamps of files in whole seconds since the epoch, 0 when not known:
+    modified: u32,
+    accessed: u32,
+    // directories sum everything below them:
+    size_on_disk: u64,
+    size_real: u64,
+}
+
+// directories are far fewer than files, so what only they need is kept apart;
+// aggregates of everything below them are kept up to date like sizes,
+// so that listing and sorting does not walk whole subtrees:
+#[derive(Clone)]
+struct Dir {
+    children_start: u32,
+    children_len: u32,
+    file_count: u32,
+    dir_count: u32,
+    newest_modified: u32,
+    oldest_modified: u32,
+    last_touched: u32,
+}
+
+#[derive(Clone)]
+pub struct Tree {
+    // path of the scanned directory, which the root node stands for:
+    root_path: PathBuf,
+    entries: Vec<Entry>,
+    dirs: Vec<Dir>,
+    // names exactly as the file system returned them, which is not always valid UTF-8:
+    names: Vec<u8>,
+    children: Vec<NodeId>,
+    // directories standing for archive files, in the order they were added, which is the order of ids:
+    archives: Vec<NodeId>,
+}
+
+impl Tree {
+    // the root is an empty directory, filled in by adding nodes and then setting its children;
+    // roots of volumes have no name of their own, so the whole path stands for it:
+    pub fn new(root_path: &Path) -> Tree {
+        let mut tree = Tree { root_path: root_path.to_path_buf(), entries: Vec::new(), dirs: Vec::new(), names: Vec::new(), children: Vec::new(), archives: Vec::new() };
+        let root_name = root_path.file_name().unwrap_or(root_path.as_os_str());
+        tree.push_entry(NO_PARENT, root_name, 0, 0, None, None);
+        tree.set_children(tree.root(), Vec::new());
+        tree
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    // including the root and removed nodes:
+    pub fn node_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn add_file(
+        &mut self,
+        parent: NodeId,
+        name: &OsStr,
+        size_on_disk: u64,
+        size_real: u64,
+        modified: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+    ) -> NodeId {
+        self.push_entry(parent.0, name, size_on_disk, size_real, modified, accessed)
+    }
+
+    // children of the directory are set once all of them are added:
+    pub fn add_dir(&mut self, parent: NodeId, name: &OsStr) -> NodeId {
+        let id = self.push_entry(parent.0, name, 0, 0, None, None);
+        self.set_children(id, Vec::new());
+        id
+    }
+
+    // sorts children by size on disk, biggest first, and sums their sizes into the directory:
+    pub fn set_children(&mut self, dir: NodeId, mut children: Vec<NodeId>) {
+        children.sort_by_key(|n| Reverse(self.size_on_disk(*n)));
+        let size_on_disk = children.iter().map(|c| self.size_on_disk(*c)).sum();
+        let size_real = children.iter().map(|c| self.size_real(*c)).sum();
+        let file_count = children.iter().map(|c| self.file_count(*c) as u32).sum();
+        let dir_count = children.iter().map(|c| self.dir_count(*c) as u32 + self.is_dir(*c) as u32).sum();
+        let record = Dir {
+            children_start: self.children.len() as u32,
+            children_len: children.len() as u32,
+            file_count,
+            dir_count,
+            newest_modified: 0,
+            oldest_modified: 0,
+            last_touched: 0,
+        };
+        let entry = &mut self.entries[dir.0 as usize];
+        entry.size_on_disk = size_on_disk;
+        entry.size_real = size_real;
+        if entry.dir == NO_DIR {
+            entry.dir = self.dirs.len() as u32;
+            self.dirs.push(record);
+        } else {
+            self.dirs[entry.dir as usize] = record;
+        }
+        self.children.extend(children);
+        self.update_timestamps(dir);
+    }
+
+    // members of the archive are not files of their own on the file system:
+    pub fn mark_archive(&mut self, id: NodeId) {
+        self.archives.push(id);
+    }
+
+    pub fn is_in_archive(&self, id: NodeId) -> bool {
+        self.ancestors(id).any(|a| self.archives.binary_search(&a).is_ok())
+    }
+
+    // for directories standing for a single file, like archives shown with their members,
+    // which count as that file in the directories above rather than as the sum of their children;
+    // to be called before the children of the parent are set:
+    pub fn set_own_sizes(&mut self, id: NodeId, size_on_disk: u64, size_real: u64) {
+        let entry = &mut self.entries[id.0 as usize];
+        entry.size_on_disk = size_on_disk;
+        entry.size_real = size_real;
+    }
+
+    // scanned directories have no timestamps of their own, but ones read back from an export may:
+    pub fn set_own_timestamps(&mut self, id: NodeId, modified: Option<SystemTime>, accessed: Option<SystemTime>) {
+        let entry = &mut self.entries[id.0 as usize];
+        entry.modified = seconds_of(modified);
+        entry.accessed = seconds_of(accessed);
+    }
+
+    // vectors grow by doubling while scanning, which leaves up to half of them unused:
+    pub fn shrink_to_fit(&mut self) {
+        self.entries.shrink_to_fit();
+        self.dirs.shrink_to_fit();
+        self.names.shrink_to_fit();
+        self.children.shrink_to_fit();
+        self.archives.shrink_to_fit();
+    }
+
+    // for showing and matching, undecodable bytes are escaped so that such names still differ:
+    pub fn name(&self, id: NodeId) -> Cow<'_, str> {
+        escaped(self.name_bytes(id))
+    }
+
+    // for file system operations, which need the name as it is:
+    pub fn os_name(&self, id: NodeId) -> &OsStr {
+        // names are pushed whole from OsStr::as_encoded_bytes and sliced at the same boundaries:
+        unsafe { OsStr::from_encoded_bytes_unchecked(self.name_bytes(id)) }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        match self.entry(id).parent {
+            NO_PARENT => None,
+            parent => Some(NodeId(parent)),
+        }
+    }
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        self.entry(id).dir != NO_DIR
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match self.dir(id) {
+            Some(dir) => {
+                let start = dir.children_start as usize;
+                &self.children[start..start + dir.children_len as usize]
+            }
+            None => &[],
+        }
+    }
+
+    pub fn size_real(&self, id: NodeId) -> u64 {
+        self.entry(id).size_real
+    }
+
+    pub fn size_on_disk(&self, id: NodeId) -> u64 {
+        self.entry(id).size_on_disk
+    }
+
+    pub fn file_count(&self, id: NodeId) -> u64 {
+        self.dir(id).map(|d| d.file_count as u64).unwrap_or(1)
+    }
+
+    // number of directories below this one, not counting itself:
+    pub fn dir_count(&self, id: NodeId) -> u64 {
+        self.dir(id).map(|d| d.dir_count as u64).unwrap_or(0)
+    }
+
+    // directories aggregate timestamps of all the files below them:
+    pub fn newest_modified(&self, id: NodeId) -> Option<SystemTime> {
+        time_of(self.newest_seconds(id))
+    }
+
+    pub fn oldest_modified(&self, id: NodeId) -> Option<SystemTime> {
+        time_of(self.oldest_seconds(id))
+    }
+
+    // timestamps of the entry itself, which scanned directories have none of:
+    pub fn modified(&self, id: NodeId) -> Option<SystemTime> {
+        time_of(self.entry(id).modified)
+    }
+
+    pub fn accessed(&self, id: NodeId) -> Option<SystemTime> {
+        time_of(self.entry(id).accessed)
+    }
+
+    // access time is not tracked on every file system, so it only counts when available:
+    pub fn last_touched(&self, id: NodeId) -> Option<SystemTime> {
+        time_of(self.touched_seconds(id))
+    }
+
+    // files count as a single entry, directories as everything below them:
+    pub fn entry_count(&self, id: NodeId) -> u64 {
+        self.file_count(id) + self.dir_count(id)
+    }
+
+    // copies the node with everything below it from another tree, which was scanned separately:
+    pub fn graft(&mut self, parent: NodeId, other: &Tree, node: NodeId) -> NodeId {
+        if !other.is_dir(node) {
+            return self.add_file(parent, other.os_name(node), other.size_on_disk(node), other.size_real(node),
+                                 other.modified(node), other.accessed(node));
+        }
+        let dir = self.add_dir(parent, other.os_name(node));
+        // marked before anything below is grafted, so that archives stay in the order of ids:
+        if other.archives.binary_search(&node).is_ok() {
+            self.mark_archive(dir);
+        }
+        let children = other.children(node).iter().map(|c| self.graft(dir, other, *c)).collect();
+        self.set_children(dir, children);
+        // the same as the sum of children, unless the directory has sizes of its own:
+        self.set_own_sizes(dir, other.size_on_disk(node), other.size_real(node));
+        dir
+    }
+
+    // directories above the node, from its parent up to the root:
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors { tree: self, next: self.parent(id) }
+    }
+
+    // everything below the node, depth first, each directory followed by its children:
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants { tree: self, stack: self.children(id).iter().rev().copied().collect() }
+    }
+
+    // directories between the base and the node, both excluded, from the top down:
+    pub fn parents_below(&self, base: NodeId, id: NodeId) -> Vec<NodeId> {
+        let mut parents: Vec<NodeId> = self.ancestors(id)
+            .take_while(|p| *p != base)
+            .collect();
+        parents.reverse();
+        parents
+    }
+
+    pub fn relative_path(&self, base: NodeId, id: NodeId) -> String {
+        let mut names: Vec<Cow<str>> = self.parents_below(base, id).iter().map(|p| self.name(*p)).collect();
+        names.push(self.name(id));
+        names.join(std::path::MAIN_SEPARATOR_STR)
+    }
+
+    pub fn relative_path_buf(&self, base: NodeId, id: NodeId) -> PathBuf {
+        let mut path: PathBuf = self.parents_below(base, id).iter().map(|p| self.os_name(*p)).collect();
+        path.push(self.os_name(id));
+        path
+    }
+
+    // full path of the node on the file system:
+    pub fn path(&self, id: NodeId) -> PathBuf {
+        if id == self.root() {
+            return self.root_path.clone();
+        }
+        self.root_path.join(self.relative_path_buf(self.root(), id))
+    }
+
+    // drops the nodes from their parents, so sizes of all the directories above them shrink
+    // and their children get sorted again; ids of the remaining nodes stay the same:
+    pub fn remove(&mut self, removed: &[NodeId]) {
+        let removed_set: HashSet<NodeId> = removed.iter().copied().collect();
+        let mut changed: Vec<NodeId> = Vec::new();
+        for id in removed {
+            // it goes away with its removed ancestor, whose sizes already include it:
+            if self.ancestors(*id).any(|a| removed_set.contains(&a)) {
+                continue;
+            }
+            let parent = match self.parent(*id) {
+                Some(p) => p,
+                None => continue,
+            };
+            let start = self.dir(parent).expect("Parent is a directory").children_start as usize;
+            let len = self.children(parent).len();
+            let position = match self.children(parent).iter().position(|c| c == id) {
+                Some(p) => p,
+                None => continue,
+            };
+            self.children.copy_within(start + position + 1..start + len, start + position);
+            self.dir_mut(parent).children_len = len as u32 - 1;
+            let (size_on_disk, size_real) = (self.size_on_disk(*id), self.size_real(*id));
+            let file_count = self.file_count(*id) as u32;
+            let dir_count = self.dir_count(*id) as u32 + self.is_dir(*id) as u32;
+            let mut ancestor = Some(parent);
+            while let Some(a) = ancestor {
+                let entry = &mut self.entries[a.0 as usize];
+                entry.size_on_disk -= size_on_disk;
+                entry.size_real -= size_real;
+                let dir = self.dir_mut(a);
+                dir.file_count -= file_count;
+                dir.dir_count -= dir_count;
+                if !changed.contains(&a) {
+                    changed.push(a);
+                }
+                ancestor = self.parent(a);
+            }
+        }
+        self.resort(changed);
+    }
+
+    // files replaced with hard links to another one keep their size, but no longer take space on disk
+    // of their own, so the directories above them shrink and get sorted again:
+    pub fn unlink_space(&mut self, relinked: &[NodeId]) {
+        let mut changed: Vec<NodeId> = Vec::new();
+        for id in relinked {
+            if self.is_dir(*id) {
+                continue;
+            }
+            let size_on_disk = std::mem::take(&mut self.entries[id.0 as usize].size_on_disk);
+            let mut ancestor = self.parent(*id);
+            while let Some(a) = ancestor {
+                self.entries[a.0 as usize].size_on_disk -= size_on_disk;
+                if !changed.contains(&a) {
+                    changed.push(a);
+                }
+                ancestor = self.parent(a);
+            }
+        }
+        self.resort(changed);
+    }
+
+    fn resort(&mut self, mut changed: Vec<NodeId>) {
+        for dir in &changed {
+            let record = self.dir(*dir).expect("Changed nodes are directories");
+            let start = record.children_start as usize;
+            let end = start + record.children_len as usize;
+            let Tree { entries, children, .. } = self;
+            children[start..end].sort_by_key(|c| Reverse(entries[c.0 as usize].size_on_disk));
+        }
+        // timestamps cannot be subtracted, so they are gathered again from the deepest directories up:
+        changed.sort_by_key(|dir| Reverse(self.ancestors(*dir).count()));
+        for dir in changed {
+            self.update_timestamps(dir);
+        }
+    }
+
+    fn update_timestamps(&mut self, dir: NodeId) {
+        let children = self.children(dir);
+        let newest_modified = children.iter().map(|c| self.newest_seconds(*c)).max().unwrap_or(0);
+        let oldest_modified = children.iter().map(|c| self.oldest_seconds(*c)).filter(|s| *s != 0).min().unwrap_or(0);
+        let last_touched = children.iter().map(|c| self.touched_seconds(*c)).max().unwrap_or(0);
+        let record = self.dir_mut(dir);
+        record.newest_modified = newest_modified;
+        record.oldest_modified = oldest_modified;
+        record.last_touched = last_touched;
+    }
+
+    fn newest_seconds(&self, id: NodeId) -> u32 {
+        self.dir(id).map(|d| d.newest_modified).unwrap_or(self.entry(id).modified)
+    }
+
+    fn oldest_seconds(&self, id: NodeId) -> u32 {
+        self.dir(id).map(|d| d.oldest_modified).unwrap_or(self.entry(id).modified)
+    }
+
+    fn touched_seconds(&self, id: NodeId) -> u32 {
+        let entry = self.entry(id);
+        self.dir(id).map(|d| d.last_touched).unwrap_or(entry.modified.max(entry.accessed))
+    }
+
+    fn entry(&self, id: NodeId) -> &Entry {
+        &self.entries[id.0 as usize]
+    }
+
+    fn dir(&self, id: NodeId) -> Option<&Dir> {
+        match self.entry(id).dir {
+            NO_DIR => None,
+            dir => Some(&self.dirs[dir as usize]),
+        }
+    }
+
+    fn dir_mut(&mut self, id: NodeId) -> &mut Dir {
+        let dir = self.entries[id.0 as usize].dir as usize;
+        &mut self.dirs[dir]
+    }
+
+    fn name_bytes(&self, id: NodeId) -> &[u8] {
+        let start = self.entry(id).name_start as usize;
+        let end = self.entries.get(id.0 as usize + 1)
+            .map(|next| next.name_start as usize)
+            .unwrap_or(self.names.len());
+        &self.names[start..end]
+    }
+
+    fn push_entry(
+        &mut self,
+        parent: u32,
+        name: &OsStr,
+        size_on_disk: u64,
+        size_real: u64,
+        modified: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+    ) -> NodeId {
+        let id = u32::try_from(self.entries.len())
+            .ok()
+            .filter(|id| *id != NO_PARENT)
+            .expect("Too many nodes for a single tree");
+        let name_start = u32::try_from(self.names.len())
+            .expect("Too many names for a single tree");
+        self.names.extend_from_slice(name.as_encoded_bytes());
+        self.entries.push(Entry {
+            name_start,
+            parent,
+            // files until children are set:
+            dir: NO_DIR,
+            modified: seconds_of(modified),
+            accessed: seconds_of(accessed),
+            size_on_disk,
+            size_real,
+        });
+        NodeId(id)
+    }
+}
+
+pub struct Ancestors<'a> {
+    tree: &'a Tree,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let current = self.next?;
+        self.next = self.tree.parent(current);
+        Some(current)
+    }
+}
+
+pub struct Descendants<'a> {
+    tree: &'a Tree,
+    stack: Vec<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let current = self.stack.pop()?;
+        self.stack.extend(self.tree.children(current).iter().rev());
+        Some(current)
+    }
+}
+
+fn escaped(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(name) = std::str::from_utf8(bytes) {
+        return Cow::Borrowed(name);
+    }
+    let mut name = String::new();
+    for chunk in bytes.utf8_chunks() {
+        name.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            name.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    Cow::Owned(name)
+}
+
+// whole seconds are enough for counting days, and fit into u32 until 2106:
+fn seconds_of(time: Option<SystemTime>) -> u32 {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs().min(u32::MAX as u64) as u32)
+        .unwrap_or(0)
+}
+
+fn time_of(seconds: u32) -> Option<SystemTime> {
+    match seconds {
+        0 => None,
+        s => Some(UNIX_EPOCH + Duration::from_secs(s as u64)),
+    }
+}
+
+// trees for tests, described the way they look; shared with the tests of the binary:
+#[cfg(test)]
+pub(crate) mod test_tree;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_tree::*;
+
+    #[test]
+    fn directories_sum_and_sort_their_children() {
+        let tree = build(dir("root", vec![
+            file("small", 1),
+            dir("a", vec![file("b", 10), dir("empty", vec![])]),
+        ]));
+        let names: Vec<Cow<str>> = tree.children(tree.root()).iter().map(|c| tree.name(*c)).collect();
+        assert_eq!(vec!["a", "small"], names);
+        assert_eq!(11, tree.size_on_disk(tree.root()));
+        assert!(tree.is_dir(find(&tree, "a/empty")));
+        assert!(!tree.is_dir(find(&tree, "a/b")));
+        assert_eq!(format!("a{}b", std::path::MAIN_SEPARATOR), tree.relative_path(tree.root(), find(&tree, "a/b")));
+    }
+
+    #[test]
+    fn removed_nodes_shrink_and_resort_directories_above() {
+        let mut tree = build(dir("root", vec![
+            dir("a", vec![file("big", 100), file("keep", 1)]),
+            dir("b", vec![file("medium", 50)]),
+        ]));
+        let a = find(&tree, "a");
+        tree.remove(&[find(&tree, "a/big")]);
+        assert_eq!(51, tree.size_real(tree.root()));
+        assert_eq!(vec!["keep"], tree.children(a).iter().map(|c| tree.name(*c)).collect::<Vec<Cow<str>>>());
+        assert_eq!("b", tree.name(tree.children(tree.root())[0]));
+    }
+
+    #[test]
+    fn nodes_removed_with_their_ancestors_are_subtracted_once() {
+        let mut tree = build(dir("root", vec![
+            dir("a", vec![file("big", 100), dir("b", vec![file("small", 1)])]),
+            file("keep", 5),
+        ]));
+        tree.remove(&[find(&tree, "a/big"), find(&tree, "a"), find(&tree, "a/b/small")]);
+        assert_eq!(5, tree.size_on_disk(tree.root()));
+        assert_eq!(1, tree.file_count(tree.root()));
+        assert_eq!(0, tree.dir_count(tree.root()));
+    }
+
+    #[test]
+    fn relinked_files_keep_their_size_but_take_no_space() {
+        let mut tree = build(dir("root", vec![
+            dir("a", vec![file("dup", 30), file("other", 5)]),
+            file("keep", 30),
+        ]));
+        let a = find(&tree, "a");
+        tree.unlink_space(&[find(&tree, "a/dup")]);
+        assert_eq!(65, tree.size_real(tree.root()));
+        assert_eq!(35, tree.size_on_disk(tree.root()));
+        assert_eq!(vec!["other", "dup"], tree.children(a).iter().map(|c| tree.name(*c)).collect::<Vec<Cow<str>>>());
+        assert_eq!("keep", tree.name(tree.children(tree.root())[0]));
+    }
+
+    #[test]
+    fn counts_include_nested_directories() {
+        let tree = build(dir("root", vec![
+            file("f1", 1),
+            dir("a", vec![file("f2", 1), dir("b", vec![])]),
+        ]));
+        assert_eq!(2, tree.file_count(tree.root()));
+        assert_eq!(2, tree.dir_count(tree.root()));
+        assert_eq!(4, tree.entry_count(tree.root()));
+    }
+
+    #[test]
+    fn iterators_walk_below_and_above_nodes() {
+        let tree = build(dir("root", vec![
+            dir("a", vec![file("b", 10), dir("c", vec![file("d", 1)])]),
+            file("e", 5),
+        ]));
+        let below: Vec<Cow<str>> = tree.descendants(tree.root()).map(|n| tree.name(n)).collect();
+        assert_eq!(vec!["a", "b", "c", "d", "e"], below);
+        let d = find(&tree, "a/c/d");
+        let above: Vec<Cow<str>> = tree.ancestors(d).map(|n| tree.name(n)).collect();
+        assert_eq!(vec!["c", "a", "root"], above);
+        assert_eq!(Path::new("root").join("a").join("c").join("d"), tree.path(d));
+    }
+
+    #[test]
+    fn timestamps_are_kept_in_whole_seconds() {
+        let mut tree = Tree::new(Path::new("root"));
+        let root = tree.root();
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let f = tree.add_file(root, OsStr::new("f"), 1, 1, Some(time), None);
+        tree.set_children(root, vec![f]);
+        assert_eq!(Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)), tree.newest_modified(root));
+        assert_eq!(None, time_of(seconds_of(None)));
+        // gathered again from what is left:
+        tree.remove(&[f]);
+        assert_eq!(None, tree.newest_modified(root));
+        assert_eq!(None, tree.last_touched(root));
+    }
+
+    #[test]
+    fn undecodable_names_are_escaped_for_showing_only() {
+        assert_eq!("plain", escaped(b"plain"));
+        assert_eq!("bad\\xFF\\xFEname", escaped(b"bad\xff\xfename"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn undecodable_names_are_kept_as_they_are() {
+        use std::os::unix::ffi::OsStrExt;
+        let mut tree = Tree::new(Path::new("root"));
+        let root = tree.root();
+        let first = tree.add_file(root, OsStr::from_bytes(b"a\xff"), 1, 1, None, None);
+        let second = tree.add_file(root, OsStr::from_bytes(b"a\xfe"), 1, 1, None, None);
+        tree.set_children(root, vec![first, second]);
+        assert_eq!(b"a\xff", tree.os_name(first).as_bytes());
+        assert_ne!(tree.name(first), tree.name(second));
+        assert_eq!(PathBuf::from(OsStr::from_bytes(b"a\xfe")), tree.relative_path_buf(root, second));
+    }
+
+    mod memory_benchmark {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use super::super::*;
+
+        struct CountingAllocator;
+
+        static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+        unsafe impl GlobalAlloc for CountingAllocator {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static GLOBAL: CountingAllocator = CountingAllocator;
+
+        // the way nodes were stored before, each of them shared on its own:
+        #[allow(dead_code)]
+        enum SharedNode {
+            File {
+                name: String,
+                size_on_disk: u64,
+                size_real: u64,
+                modified: Option<SystemTime>,
+                accessed: Option<SystemTime>,
+            },
+            Dir { name: String, nodes: Vec<Arc<SharedNode>> },
+        }
+
+        const DIRS: usize = 1_000;
+        const FILES_PER_DIR: usize = 1_000;
+
+        fn measure<T, F: FnOnce() -> T>(build: F) -> (T, usize) {
+            let before = ALLOCATED.load(Ordering::Relaxed);
+            let built = build();
+            (built, ALLOCATED.load(Ordering::Relaxed) - before)
+        }
+
+        fn shared_nodes() -> Arc<SharedNode> {
+            let dirs = (0..DIRS)
+                .map(|d| {
+                    let files = (0..FILES_PER_DIR)
+                        .map(|f| Arc::new(SharedNode::File {
+                            name: format!("file_{:06}.dat", f),
+                            size_on_disk: 4096,
+                            size_real: f as u64,
+                            modified: Some(SystemTime::now()),
+                            accessed: Some(SystemTime::now()),
+                        }))
+                        .collect();
+                    Arc::new(SharedNode::Dir { name: format!("dir_{:06}", d), nodes: files })
+                })
+                .collect();
+            Arc::new(SharedNode::Dir { name: "root".to_string(), nodes: dirs })
+        }
+
+        fn tree() -> Tree {
+            let mut tree = Tree::new(Path::new("root"));
+            let root = tree.root();
+            let mut dirs: Vec<NodeId> = Vec::new();
+            for d in 0..DIRS {
+                let dir = tree.add_dir(root, OsStr::new(&format!("dir_{:06}", d)));
+                let files: Vec<NodeId> = (0..FILES_PER_DIR)
+                    .map(|f| tree.add_file(dir, OsStr::new(&format!("file_{:06}.dat", f)), 4096, f as u64, Some(SystemTime::now()), Some(SystemTime::now())))
+                    .collect();
+                tree.set_children(dir, files);
+                dirs.push(dir);
+            }
+            tree.set_children(root, dirs);
+            // the same as the scanner does:
+            tree.shrink_to_fit();
+            tree
+        }
+
+        // allocations of other tests would be counted as well, so it is run on its own:
+        // cargo test --release memory_benchmark -- --ignored --nocapture
+        #[test]
+        #[ignore]
+        fn tree_takes_less_memory_than_shared_nodes() {
+            let (shared, shared_bytes) = measure(shared_nodes);
+            drop(shared);
+            let (tree, tree_bytes) = measure(tree);
+            let nodes = DIRS * FILES_PER_DIR + DIRS + 1;
+            println!("shared nodes: {} bytes, {} per node", shared_bytes, shared_bytes / nodes);
+            println!("tree: {} bytes, {} per node", tree_bytes, tree_bytes / nodes);
+            drop(tree);
+            assert!(tree_bytes * 3 < shared_bytes * 2);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+// both the library and the binary include this file, each of them having these in the parent module:
+use super::{NodeId, Tree};
+
+pub(crate) enum TestNode {
+    File(&'static str, u64),
+    Dir(&'static str, Vec<TestNode>),
+}
+
+pub(crate) fn file(name: &'static str, size: u64) -> TestNode {
+    TestNode::File(name, size)
+}
+
+pub(crate) fn dir(name: &'static str, nodes: Vec<TestNode>) -> TestNode {
+    TestNode::Dir(name, nodes)
+}
+
+// the name of the root is the path the tree is scanned from:
+pub(crate) fn build(root: TestNode) -> Tree {
+    let (name, nodes) = match root {
+        TestNode::Dir(name, nodes) => (name, nodes),
+        TestNode::File(..) => panic!("root of a test tree has to be a directory"),
+    };
+    let mut tree = Tree::new(Path::new(name));
+    let root = tree.root();
+    add_nodes(&mut tree, root, nodes);
+    tree
+}
+
+// with several children of the same name, the first one in the order of size on disk is found:
+pub(crate) fn find(tree: &Tree, path: &str) -> NodeId {
+    let mut id = tree.root();
+    for name in path.split('/') {
+        id = *tree.children(id).iter()
+            .find(|c| tree.name(**c) == name)
+            .unwrap_or_else(|| panic!("{} not found in the test tree", path));
+    }
+    id
+}
+
+fn add_nodes(tree: &mut Tree, parent: NodeId, nodes: Vec<TestNode>) {
+    let mut children: Vec<NodeId> = Vec::new();
+    for node in nodes {
+        match node {
+            TestNode::File(name, size) => children.push(tree.add_file(parent, OsStr::new(name), size, size, None, None)),
+            TestNode::Dir(name, nodes) => {
+                let id = tree.add_dir(parent, OsStr::new(name));
+                add_nodes(tree, id, nodes);
+                children.push(id);
+            }
+        }
+    }
+    tree.set_children(parent, children);
+}
@@ -0,0 +1,306 @@
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use windows::core::{HSTRING};
+#[cfg(windows)]
+use windows::Win32::Storage::FileSystem::{GetVolumePathNameW, GetDiskFreeSpaceW, GetLogicalDriveStringsW, GetVolumeInformationW};
+
+// volumes are looked at through the Windows API, elsewhere there are plain fallbacks,
+// so that the library can be used on any platform:
+#[cfg(windows)]
+pub(crate) fn cluster_size_for_path(path: &Path) -> io::Result<u64> {
+    let root_path: &mut [u16] = &mut [0; 261];
+    let path_result = unsafe { GetVolumePathNameW(&HSTRING::from(path), root_path) };
+    if !path_result.as_bool() {
+        return Err(io::Error::other(format!("Could not determine volume name for path: {:?}", path)));
+    }
+    let mut sectors_per_cluster: u32 = 0;
+    let mut bytes_per_sector: u32 = 0;
+    let root_disk = HSTRING::from_wide(root_path).map_err(|e| io::Error::other(e.to_string()))?;
+    let disk_free_result = unsafe { GetDiskFreeSpaceW(&root_disk, Some(&mut sectors_per_cluster), Some(&mut bytes_per_sector), None, None) };
+    if !disk_free_result.as_bool() {
+        return Err(io::Error::other(format!("Could not determine cluster size for disk of path: {:?}", path)));
+    }
+    Ok(u64::from(sectors_per_cluster) * u64::from(bytes_per_sector))
+}
+
+// the block size the file system prefers, which is usually what files are allocated in:
+#[cfg(not(windows))]
+pub(crate) fn cluster_size_for_path(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::metadata(path)?.blksize())
+}
+
+// the same for all hard links to a file: serial number of its volume and index of the file on it:
+#[cfg(windows)]
+pub fn file_id(path: &Path) -> io::Result<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+    let file = std::fs::File::open(path)?;
+    let mut info = BY_HANDLE_FILE_INFORMATION::default();
+    let result = unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle() as isize), &mut info) };
+    if !result.as_bool() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((u64::from(info.dwVolumeSerialNumber), u64::from(info.nFileIndexHigh) << 32 | u64::from(info.nFileIndexLow)))
+}
+
+// device and inode elsewhere:
+#[cfg(not(windows))]
+pub fn file_id(path: &Path) -> io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+// free space available to the user and total space of the volume containing the path:
+#[cfg(windows)]
+pub fn volume_space(path: &Path) -> Option<(u64, u64)> {
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let mut free: u64 = 0;
+    let mut total: u64 = 0;
+    let result = unsafe { GetDiskFreeSpaceExW(&HSTRING::from(path), Some(&mut free), Some(&mut total), None) };
+    if !result.as_bool() {
+        eprintln!("Could not determine free space for path: {:?}", path);
+        return None;
+    }
+    Some((free, total))
+}
+
+// elsewhere the space is counted in fragments of the file system, with field types differing between platforms:
+#[cfg(not(windows))]
+#[allow(clippy::unnecessary_cast)]
+pub fn volume_space(path: &Path) -> Option<(u64, u64)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        eprintln!("Could not determine free space for path: {:?}", path);
+        return None;
+    }
+    let fragment = stats.f_frsize as u64;
+    Some((stats.f_bavail as u64 * fragment, stats.f_blocks as u64 * fragment))
+}
+
+// roots of mounted volumes, like C:\, in the order of drive letters:
+#[cfg(windows)]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    let buffer: &mut [u16] = &mut [0; 1024];
+    let length = unsafe { GetLogicalDriveStringsW(Some(buffer)) } as usize;
+    if length == 0 || length > buffer.len() {
+        eprintln!("Could not list mounted volumes");
+        return Vec::new();
+    }
+    // the roots are separated by NULs:
+    buffer[..length].split(|c| *c == 0)
+        .filter(|root| !root.is_empty())
+        .map(|root| PathBuf::from(String::from_utf16_lossy(root)))
+        .collect()
+}
+
+// mount points of file systems holding files, in the order they were mounted:
+#[cfg(target_os = "linux")]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    let volumes: Vec<PathBuf> = mounts().into_iter().map(|(_, mount_point)| mount_point).collect();
+    if volumes.is_empty() {
+        return vec![PathBuf::from("/")];
+    }
+    volumes
+}
+
+#[cfg(target_os = "macos")]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    use std::ffi::CStr;
+    use std::os::unix::ffi::OsStrExt;
+    let mut buffer: *mut libc::statfs = std::ptr::null_mut();
+    let count = unsafe { libc::getmntinfo(&mut buffer, libc::MNT_NOWAIT) };
+    if count <= 0 {
+        eprintln!("Could not list mounted volumes");
+        return vec![PathBuf::from("/")];
+    }
+    // the buffer belongs to the system and is reused by the next call:
+    let mounts = unsafe { std::slice::from_raw_parts(buffer, count as usize) };
+    mounts.iter()
+        .filter(|m| !is_pseudo_file_system(unsafe { CStr::from_ptr(m.f_fstypename.as_ptr()) }.to_bytes()))
+        .map(|m| PathBuf::from(std::ffi::OsStr::from_bytes(unsafe { CStr::from_ptr(m.f_mntonname.as_ptr()) }.to_bytes())))
+        .collect()
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    vec![PathBuf::from("/")]
+}
+
+// empty for volumes without a label, None for ones not ready, like drives without a disc:
+#[cfg(windows)]
+pub fn volume_label(path: &Path) -> Option<String> {
+    let label: &mut [u16] = &mut [0; 261];
+    let result = unsafe { GetVolumeInformationW(&HSTRING::from(path), Some(label), None, None, None, None) };
+    if !result.as_bool() {
+        return None;
+    }
+    let length = label.iter().position(|c| *c == 0).unwrap_or(label.len());
+    Some(String::from_utf16_lossy(&label[..length]))
+}
+
+// labels are links to the devices they name, with spaces and other special characters escaped:
+#[cfg(target_os = "linux")]
+pub fn volume_label(path: &Path) -> Option<String> {
+    let device = mounts().into_iter()
+        .find(|(_, mount_point)| mount_point == path)
+        .and_then(|(device, _)| std::fs::canonicalize(device).ok())?;
+    let labels = match std::fs::read_dir("/dev/disk/by-label") {
+        Ok(labels) => labels,
+        // none of the volumes has a label:
+        Err(_) => return Some(String::new()),
+    };
+    let label = labels.filter_map(|l| l.ok())
+        .find(|l| std::fs::canonicalize(l.path()).ok().as_ref() == Some(&device))
+        .map(|l| String::from_utf8_lossy(&unescape(l.file_name().as_encoded_bytes(), b"\\x", 16, 2)).into_owned());
+    Some(label.unwrap_or_default())
+}
+
+// volumes are mounted under /Volumes by their names, the startup one is the root:
+#[cfg(target_os = "macos")]
+pub fn volume_label(path: &Path) -> Option<String> {
+    match path.strip_prefix("/Volumes") {
+        Ok(name) => Some(name.to_string_lossy().into_owned()),
+        Err(_) => Some(String::new()),
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn volume_label(_path: &Path) -> Option<String> {
+    Some(String::new())
+}
+
+// serial number of the volume containing the path, which tells whether two paths are on the same one:
+#[cfg(windows)]
+pub fn device_id(path: &Path) -> io::Result<u64> {
+    volume_serial_number(path)
+        .map(u64::from)
+        .ok_or_else(|| io::Error::other(format!("Could not determine volume of {:?}", path)))
+}
+
+// the device the file system is on:
+#[cfg(not(windows))]
+pub fn device_id(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(std::fs::metadata(path)?.dev())
+}
+
+#[cfg(windows)]
+fn volume_serial_number(path: &Path) -> Option<u32> {
+    let root_path: &mut [u16] = &mut [0; 261];
+    let path_result = unsafe { GetVolumePathNameW(&HSTRING::from(path), root_path) };
+    if !path_result.as_bool() {
+        return None;
+    }
+    let root_disk = HSTRING::from_wide(root_path).ok()?;
+    let mut serial_number: u32 = 0;
+    let result = unsafe { GetVolumeInformationW(&root_disk, None, Some(&mut serial_number), None, None, None) };
+    if !result.as_bool() {
+        return None;
+    }
+    Some(serial_number)
+}
+
+// devices and mount points from the mount table, leaving out file systems of the kernel:
+#[cfg(target_os = "linux")]
+fn mounts() -> Vec<(PathBuf, PathBuf)> {
+    match std::fs::read("/proc/self/mounts") {
+        Ok(table) => parse_mounts(&table),
+        Err(e) => {
+            eprintln!("Could not read the mount table, because of: {:?}", e);
+            Vec::new()
+        }
+    }
+}
+
+// each line has the device, mount point, file system type and options separated by spaces,
+// so spaces, tabs, newlines and backslashes in them are escaped as octal numbers, like \040:
+#[cfg(target_os = "linux")]
+fn parse_mounts(table: &[u8]) -> Vec<(PathBuf, PathBuf)> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    let mut mounts: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for line in table.split(|b| *b == b'\n') {
+        let mut fields = line.split(|b| *b == b' ');
+        let (device, mount_point, fs_type) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(device), Some(mount_point), Some(fs_type)) => (device, mount_point, fs_type),
+            _ => continue,
+        };
+        if is_pseudo_file_system(fs_type) {
+            continue;
+        }
+        let mount_point = PathBuf::from(OsString::from_vec(unescape(mount_point, b"\\", 8, 3)));
+        // mounted over again, the last one is what is seen there:
+        mounts.retain(|(_, m)| *m != mount_point);
+        mounts.push((PathBuf::from(OsString::from_vec(unescape(device, b"\\", 8, 3))), mount_point));
+    }
+    mounts
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn is_pseudo_file_system(fs_type: &[u8]) -> bool {
+    const PSEUDO: &[&[u8]] = &[
+        b"autofs", b"binfmt_misc", b"bpf", b"cgroup", b"cgroup2", b"configfs", b"debugfs", b"devfs", b"devpts",
+        b"devtmpfs", b"efivarfs", b"fusectl", b"hugetlbfs", b"mqueue", b"nsfs", b"proc", b"pstore", b"ramfs",
+        b"rpc_pipefs", b"securityfs", b"squashfs", b"sysfs", b"tmpfs", b"tracefs",
+    ];
+    PSEUDO.contains(&fs_type)
+}
+
+// escapes are a marker followed by a fixed number of digits,
+// like \040 in the mount table or \x20 in the names of labels:
+#[cfg(target_os = "linux")]
+fn unescape(bytes: &[u8], marker: &[u8], radix: u32, digits: usize) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i + marker.len();
+        let escaped = bytes[i..].starts_with(marker)
+            .then(|| bytes.get(start..start + digits))
+            .flatten()
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u8::from_str_radix(d, radix).ok());
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                i = start + digits;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_space_is_known_for_existing_paths() {
+        let (free, total) = volume_space(&std::env::temp_dir()).unwrap();
+        assert!(total > 0);
+        assert!(free <= total);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mount_table_leaves_out_kernel_file_systems() {
+        let table = b"proc /proc proc rw 0 0\n/dev/sda1 / ext4 rw 0 0\ntmpfs /run tmpfs rw 0 0\n/dev/sdb1 /media/My\\040Disk vfat rw 0 0\n";
+        let mounts = parse_mounts(table);
+        assert_eq!(vec![PathBuf::from("/"), PathBuf::from("/media/My Disk")],
+                   mounts.iter().map(|(_, m)| m.clone()).collect::<Vec<_>>());
+        assert_eq!(PathBuf::from("/dev/sdb1"), mounts[1].0);
+        assert_eq!(b"a\\b".to_vec(), unescape(b"a\\134b", b"\\", 8, 3));
+        assert_eq!(b"My Disk".to_vec(), unescape(b"My\\x20Disk", b"\\x", 16, 2));
+        assert_eq!(b"a\\x2".to_vec(), unescape(b"a\\x2", b"\\x", 16, 2));
+    }
+}